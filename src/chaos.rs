@@ -0,0 +1,97 @@
+use axum::{
+    body::Body,
+    http::{StatusCode, Version},
+    response::Response,
+};
+use http_body_util::BodyExt;
+
+use crate::config::Fault;
+use crate::connection::Connection;
+
+/// Apply `fault` to the response that was about to be sent.
+///
+/// Returns the response to send instead, or a description of what was
+/// done when the fault has taken over the connection and the handler must
+/// abandon it.
+pub async fn inject(
+    fault: &Fault,
+    response: Response,
+    connection: &Connection,
+    version: Version,
+) -> Result<Response, &'static str> {
+    let raw_writes_possible = matches!(version, Version::HTTP_10 | Version::HTTP_11);
+
+    match fault {
+        Fault::Error { status, body } => {
+            let status = StatusCode::from_u16(*status).unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+            let body = body
+                .clone()
+                .unwrap_or_else(|| status.canonical_reason().unwrap_or("").to_string());
+            Ok(Response::builder()
+                .status(status)
+                .body(Body::from(body))
+                .unwrap())
+        }
+        Fault::Truncate if raw_writes_possible => {
+            let (head, body) = split_response(response).await;
+            // Always promise more than is sent, even for an empty body
+            let declared = body.len().max(1);
+            let mut raw = head;
+            raw.extend_from_slice(format!("content-length: {}\r\n\r\n", declared).as_bytes());
+            raw.extend_from_slice(&body[..body.len() / 2]);
+            let _ = connection.write_raw(&raw).await;
+            Err("sent half the body, then closed the connection (fault: truncate)")
+        }
+        Fault::MalformedHeaders if raw_writes_possible => {
+            let (head, body) = split_response(response).await;
+            let mut raw = head;
+            raw.extend_from_slice(b"Content-Type application/json\r\n");
+            raw.extend_from_slice(b"X-Broken Header\x01: value\r\n");
+            raw.extend_from_slice(b"X-Folded: first\r\n  continued\r\n");
+            raw.extend_from_slice(format!("content-length: {}\r\n\r\n", body.len()).as_bytes());
+            raw.extend_from_slice(&body);
+            let _ = connection.write_raw(&raw).await;
+            Err("sent malformed headers, then closed the connection (fault: malformed_headers)")
+        }
+        Fault::Reset => {
+            connection.reset();
+            Err("reset the connection (fault: reset)")
+        }
+        // Raw writes would corrupt HTTP/2 framing, so fall back to a reset
+        Fault::Truncate | Fault::MalformedHeaders => {
+            connection.reset();
+            Err("reset the connection (HTTP/2 fallback for truncate and malformed_headers faults)")
+        }
+    }
+}
+
+/// Serialize a response's status line and headers (without Content-Length
+/// or the terminating blank line), and collect its body.
+async fn split_response(response: Response) -> (Vec<u8>, Vec<u8>) {
+    let (parts, body) = response.into_parts();
+
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        parts.status.as_u16(),
+        parts.status.canonical_reason().unwrap_or("")
+    )
+    .into_bytes();
+    for (name, value) in parts
+        .headers
+        .iter()
+        .filter(|(name, _)| *name != "content-length")
+    {
+        head.extend_from_slice(name.as_str().as_bytes());
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value.as_bytes());
+        head.extend_from_slice(b"\r\n");
+    }
+
+    let body = body
+        .collect()
+        .await
+        .map(|b| b.to_bytes().to_vec())
+        .unwrap_or_default();
+
+    (head, body)
+}
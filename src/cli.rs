@@ -0,0 +1,149 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use crate::config::ConfigOverrides;
+
+/// A CLI tool for local webhook testing with a TUI interface
+#[derive(Debug, Parser)]
+#[command(
+    name = "webhook",
+    version,
+    about,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the server (the default when no subcommand is given)
+    Serve(ServeArgs),
+    /// Validate the config file and exit
+    Check(ConfigArgs),
+    /// List saved sessions
+    Sessions,
+    /// Export a saved session as a HAR file
+    Export(ExportArgs),
+    /// Send requests from a saved session to another server
+    Replay(ReplayArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigArgs {
+    /// Config file to use instead of searching the default locations
+    #[arg(short, long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ExportArgs {
+    /// Session name, or path to a session .jsonl file
+    pub session: String,
+
+    /// File to write instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Only export the request with this id (repeatable)
+    #[arg(long = "id", value_name = "ID")]
+    pub ids: Vec<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// Session name, or path to a session .jsonl file
+    pub session: String,
+
+    /// Base URL to send requests to, overriding replay.target in the config
+    #[arg(long, value_name = "URL")]
+    pub to: Option<String>,
+
+    /// Only replay the request with this id (repeatable)
+    #[arg(long = "id", value_name = "ID")]
+    pub ids: Vec<u64>,
+
+    /// Send the original Host header instead of the target's
+    #[arg(long)]
+    pub preserve_host: bool,
+
+    /// Set a header on replayed requests, as "Name: value"; "Name:" removes
+    /// it (repeatable)
+    #[arg(short = 'H', long = "header", value_name = "HEADER", value_parser = parse_header)]
+    pub headers: Vec<(String, String)>,
+}
+
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected \"Name: value\", got {:?}", value))?;
+    Ok((name.trim().to_string(), value.trim().to_string()))
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+
+    /// Port to listen on, overriding the config file
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Address to bind, overriding the config file
+    #[arg(long)]
+    pub host: Option<String>,
+
+    /// Stream captured requests instead of starting the TUI
+    #[arg(long, alias = "headless")]
+    pub no_tui: bool,
+
+    /// Format of captured requests in --no-tui mode
+    #[arg(long, value_enum, default_value_t = OutputFormat::Jsonl)]
+    pub format: OutputFormat,
+
+    /// Write captured requests to a file instead of stdout in --no-tui mode
+    #[arg(short, long, value_name = "FILE", requires = "no_tui")]
+    pub output: Option<PathBuf>,
+
+    /// Save captured requests to a named session, restoring any it already
+    /// holds. A path ending in .jsonl may be given instead of a name
+    #[arg(short, long, value_name = "NAME")]
+    pub session: Option<String>,
+
+    /// Base URL to replay requests to, overriding replay.target in the config
+    #[arg(long, value_name = "URL")]
+    pub replay_to: Option<String>,
+
+    /// Append diagnostic messages (startup, reloads, errors) to a file
+    #[arg(long, value_name = "FILE")]
+    pub log: Option<PathBuf>,
+
+    /// Validate the config file and exit (same as `webhook check`)
+    #[arg(long)]
+    pub check_config: bool,
+}
+
+impl ServeArgs {
+    pub fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            port: self.port,
+            host: self.host.clone(),
+            replay_target: self.replay_to.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One JSON object per request, per line
+    Jsonl,
+    /// One human-readable summary line per request
+    Text,
+}
@@ -0,0 +1,266 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::request::CapturedRequest;
+
+/// Turn away requests whose CloudEvents break the spec.
+///
+/// ```yaml
+/// cloudevents:
+///   reject: true      # answer 400 to invalid events
+///   require: true     # and to requests that are not CloudEvents at all
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CloudEventsConfig {
+    #[serde(default)]
+    pub reject: bool,
+    /// With `reject`, also turn away requests that carry no CloudEvent.
+    #[serde(default)]
+    pub require: bool,
+}
+
+/// The CloudEvents carried by a request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudEvents {
+    pub mode: Mode,
+    pub events: Vec<CloudEvent>,
+    /// Why the body could not be read as events at all.
+    pub error: Option<String>,
+}
+
+/// How the event was put into the HTTP message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Attributes in `ce-` headers, data in the body.
+    Binary,
+    /// The whole event as `application/cloudevents+json`.
+    Structured,
+    /// An array of structured events as `application/cloudevents-batch+json`.
+    Batch,
+}
+
+impl Mode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Mode::Binary => "binary",
+            Mode::Structured => "structured",
+            Mode::Batch => "batch",
+        }
+    }
+}
+
+/// One event's context attributes. Its data is the request body, or the
+/// `data` member in structured mode, so it is not repeated here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloudEvent {
+    pub attributes: BTreeMap<String, String>,
+    /// Ways the event breaks the spec; empty when it is valid.
+    pub problems: Vec<String>,
+}
+
+const REQUIRED: [&str; 4] = ["id", "source", "specversion", "type"];
+
+/// Attributes defined by the spec, in the order they are shown. Anything
+/// else is an extension.
+pub const CORE_ATTRIBUTES: [&str; 8] = [
+    "id",
+    "source",
+    "type",
+    "subject",
+    "time",
+    "specversion",
+    "datacontenttype",
+    "dataschema",
+];
+
+impl CloudEvent {
+    fn new(attributes: BTreeMap<String, String>, mut problems: Vec<String>) -> Self {
+        for name in REQUIRED {
+            if attributes.get(name).is_none_or(|v| v.is_empty()) {
+                problems.push(format!("missing required attribute {}", name));
+            }
+        }
+        if let Some(version) = attributes.get("specversion").filter(|v| !v.is_empty()) {
+            if version != "1.0" {
+                problems.push(format!(
+                    "unsupported specversion {:?}, expected 1.0",
+                    version
+                ));
+            }
+        }
+        if let Some(time) = attributes.get("time") {
+            if DateTime::parse_from_rfc3339(time).is_err() {
+                problems.push(format!("time {:?} is not an RFC 3339 timestamp", time));
+            }
+        }
+        for name in attributes.keys() {
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit());
+            if !valid {
+                problems.push(format!(
+                    "attribute name {:?} must be lower-case letters and digits",
+                    name
+                ));
+            }
+        }
+        Self {
+            attributes,
+            problems,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+
+    /// Attributes that are not part of the spec, such as `traceparent`.
+    pub fn extensions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .filter(|(k, _)| !CORE_ATTRIBUTES.contains(&k.as_str()))
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl CloudEvents {
+    /// Every problem found, prefixed with the event's position in a batch.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = self.error.iter().cloned().collect();
+        for (i, event) in self.events.iter().enumerate() {
+            for problem in &event.problems {
+                problems.push(match self.mode {
+                    Mode::Batch => format!("events[{}]: {}", i, problem),
+                    _ => problem.clone(),
+                });
+            }
+        }
+        problems
+    }
+}
+
+/// Read the CloudEvents in `request`, if it uses any of the HTTP binding's
+/// modes: `ce-` headers including `ce-specversion`, or a CloudEvents JSON
+/// content type.
+pub fn parse(request: &CapturedRequest) -> Option<CloudEvents> {
+    let content_type = request
+        .header("content-type")
+        .and_then(|ct| ct.split(';').next())
+        .map(|ct| ct.trim().to_ascii_lowercase());
+
+    match content_type.as_deref() {
+        Some("application/cloudevents+json") => {
+            let (events, error) = match request.json_body() {
+                Some(json) => (vec![structured(&json)], None),
+                None => (Vec::new(), Some("body is not valid JSON".to_string())),
+            };
+            return Some(CloudEvents {
+                mode: Mode::Structured,
+                events,
+                error,
+            });
+        }
+        Some("application/cloudevents-batch+json") => {
+            let (events, error) = match request.json_body() {
+                Some(Value::Array(items)) => (items.iter().map(structured).collect(), None),
+                _ => (Vec::new(), Some("body is not a JSON array".to_string())),
+            };
+            return Some(CloudEvents {
+                mode: Mode::Batch,
+                events,
+                error,
+            });
+        }
+        _ => {}
+    }
+
+    let mut attributes = BTreeMap::new();
+    for (name, value) in request.headers.iter() {
+        if let Some(attribute) = name.to_ascii_lowercase().strip_prefix("ce-") {
+            attributes.insert(attribute.to_string(), percent_decode(value));
+        }
+    }
+    // Other `ce-` headers alone are not enough to call a request an event
+    if !attributes.contains_key("specversion") {
+        return None;
+    }
+    if let Some(content_type) = request.header("content-type") {
+        attributes.insert("datacontenttype".to_string(), content_type.to_string());
+    }
+
+    Some(CloudEvents {
+        mode: Mode::Binary,
+        events: vec![CloudEvent::new(attributes, Vec::new())],
+        error: None,
+    })
+}
+
+/// An event in the JSON event format. Scalar attributes are kept as text;
+/// `data` and `data_base64` are left out.
+fn structured(json: &Value) -> CloudEvent {
+    let Some(object) = json.as_object() else {
+        return CloudEvent {
+            attributes: BTreeMap::new(),
+            problems: vec!["event is not a JSON object".to_string()],
+        };
+    };
+
+    let mut attributes = BTreeMap::new();
+    let mut problems = Vec::new();
+    for (name, value) in object {
+        let text = match value {
+            _ if name == "data" => continue,
+            _ if name == "data_base64" => {
+                let decoded = value.as_str().map(|v| BASE64.decode(v));
+                if !matches!(decoded, Some(Ok(_))) {
+                    problems.push("data_base64 is not a base64 string".to_string());
+                }
+                continue;
+            }
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Null => continue,
+            Value::Array(_) | Value::Object(_) => {
+                problems.push(format!("attribute {} must not be an array or object", name));
+                continue;
+            }
+        };
+        attributes.insert(name.clone(), text);
+    }
+
+    if object.contains_key("data") && object.contains_key("data_base64") {
+        problems.push("data and data_base64 are both set".to_string());
+    }
+
+    CloudEvent::new(attributes, problems)
+}
+
+/// Header values are percent-encoded outside printable ASCII.
+fn percent_decode(value: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut i = 0;
+    while i < value.len() {
+        let hex = value
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (value[i], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
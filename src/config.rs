@@ -1,9 +1,25 @@
+use axum::http::{HeaderName, Method, StatusCode};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::cloudevents::CloudEventsConfig;
+use crate::matcher::{MatchConditions, PathPattern};
+use crate::request::CapturedRequest;
+use crate::responder::Responder;
+use crate::signature::{SignatureConfig, SignatureScheme};
+use crate::template;
+use crate::upstream;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct ResponseConfig {
     #[serde(default = "default_status")]
     pub status: u16,
@@ -11,6 +27,174 @@ pub struct ResponseConfig {
     pub headers: HashMap<String, String>,
     #[serde(default = "default_body")]
     pub body: String,
+    #[serde(default)]
+    pub delay: Option<DelayConfig>,
+    #[serde(default)]
+    pub mode: ResponseMode,
+    #[serde(default)]
+    pub chaos: Vec<FaultConfig>,
+}
+
+/// How long to wait before answering, in milliseconds.
+///
+/// ```yaml
+/// delay: 250                                  # fixed
+/// delay: { min: 100, max: 900 }               # uniform range
+/// delay: { mean: 300, stddev: 80 }            # normal distribution
+/// delay: { mean: 300, distribution: exponential }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum DelayConfig {
+    Fixed(u64),
+    Range {
+        min: u64,
+        max: u64,
+    },
+    Distribution {
+        mean: f64,
+        #[serde(default)]
+        stddev: f64,
+        #[serde(default)]
+        distribution: Distribution,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Distribution {
+    #[default]
+    Normal,
+    Exponential,
+}
+
+impl DelayConfig {
+    pub fn sample(&self) -> Duration {
+        let mut rng = rand::rng();
+        let millis = match *self {
+            DelayConfig::Fixed(ms) => ms as f64,
+            DelayConfig::Range { min, max } => rng.random_range(min.min(max)..=max.max(min)) as f64,
+            DelayConfig::Distribution {
+                mean,
+                stddev,
+                distribution: Distribution::Normal,
+            } => {
+                // Box-Muller transform
+                let u1: f64 = 1.0 - rng.random::<f64>();
+                let u2: f64 = rng.random();
+                mean + stddev * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            }
+            DelayConfig::Distribution {
+                mean,
+                distribution: Distribution::Exponential,
+                ..
+            } => -mean * (1.0 - rng.random::<f64>()).ln(),
+        };
+        Duration::from_millis(millis.max(0.0).round() as u64)
+    }
+}
+
+/// A fault injected into a percentage of responses.
+///
+/// ```yaml
+/// chaos:
+///   - { fault: error, percent: 20, status: 503 }
+///   - { fault: reset, percent: 5 }
+///   - { fault: truncate, percent: 5 }
+///   - { fault: malformed_headers, percent: 5 }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "FaultFields")]
+pub struct FaultConfig {
+    pub percent: f64,
+    #[serde(flatten)]
+    pub fault: Fault,
+}
+
+/// A `chaos` entry as written. Keys next to a `flatten`ed field are never
+/// reported as unknown, so everything but `percent` is parsed as a `Fault`
+/// on its own.
+#[derive(Deserialize)]
+struct FaultFields {
+    percent: f64,
+    #[serde(flatten)]
+    fault: serde_yaml::Mapping,
+}
+
+impl TryFrom<FaultFields> for FaultConfig {
+    type Error = serde_yaml::Error;
+
+    fn try_from(fields: FaultFields) -> Result<Self, Self::Error> {
+        let fault: Fault =
+            serde_yaml::from_value(serde_yaml::Value::Mapping(fields.fault.clone()))?;
+        // Unit variants accept any keys, so check those by hand
+        if !matches!(fault, Fault::Error { .. }) {
+            if let Some(key) = fields
+                .fault
+                .keys()
+                .find(|key| key.as_str() != Some("fault"))
+            {
+                return Err(serde::de::Error::custom(format!(
+                    "unknown field `{}`, expected `fault` or `percent`",
+                    key.as_str().unwrap_or("?")
+                )));
+            }
+        }
+        Ok(FaultConfig {
+            percent: fields.percent,
+            fault,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "fault", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Fault {
+    /// Replace the response with an error status.
+    Error {
+        #[serde(default = "default_fault_status")]
+        status: u16,
+        #[serde(default)]
+        body: Option<String>,
+    },
+    /// Reset the TCP connection instead of responding.
+    Reset,
+    /// Send the headers and only half of the body, then close.
+    Truncate,
+    /// Send a response with syntactically invalid headers, then close.
+    MalformedHeaders,
+}
+
+fn default_fault_status() -> u16 {
+    503
+}
+
+/// Pick at most one fault to inject. Each fault claims its own slice of
+/// the percentage range, so `percent` values should add up to at most 100.
+pub fn roll_chaos(faults: &[FaultConfig]) -> Option<&Fault> {
+    if faults.is_empty() {
+        return None;
+    }
+
+    let roll = rand::rng().random::<f64>() * 100.0;
+    let mut threshold = 0.0;
+    faults.iter().find_map(|f| {
+        threshold += f.percent;
+        (roll < threshold).then_some(&f.fault)
+    })
+}
+
+/// What to do with the connection once any delay has elapsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseMode {
+    /// Send the configured response.
+    #[default]
+    Respond,
+    /// Never respond; hold the connection until the client gives up.
+    Hang,
+    /// Close the connection without sending anything.
+    Close,
 }
 
 fn default_status() -> u16 {
@@ -29,19 +213,201 @@ impl Default for ResponseConfig {
             status: 200,
             headers,
             body: r#"{"status": "ok"}"#.to_string(),
+            delay: None,
+            mode: ResponseMode::Respond,
+            chaos: Vec::new(),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct RouteConfig {
-    pub path: String,
+    pub path: PathPattern,
     #[serde(default)]
     pub method: Option<String>,
+    #[serde(default, rename = "match")]
+    pub conditions: MatchConditions,
+    #[serde(default)]
+    pub responder: Option<Responder>,
+    #[serde(default)]
     pub response: ResponseConfig,
+    #[serde(default)]
+    pub sequence: Option<SequenceConfig>,
+    /// Pass matching requests on to an upstream and relay its response,
+    /// instead of answering with `response`.
+    #[serde(default)]
+    pub forward: Option<ForwardConfig>,
+    /// Verify the provider's signature on matching requests.
+    #[serde(default)]
+    pub signature: Option<SignatureConfig>,
+    /// Check that matching requests are valid CloudEvents.
+    #[serde(default)]
+    pub cloudevents: Option<CloudEventsConfig>,
 }
 
+/// An upstream to pass requests through to, and optionally others that get
+/// a copy of each request. Only `target`'s response goes back to the caller.
+///
+/// ```yaml
+/// forward:
+///   target: "http://localhost:3000"
+///   preserve_host: true
+///   headers:
+///     X-Forwarded-Proto: "https"
+///   mirror:
+///     - target: "https://teammate.ngrok.app"
+/// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ForwardFields")]
+pub struct ForwardConfig {
+    #[serde(flatten)]
+    pub upstream: UpstreamConfig,
+    /// Upstreams sent a copy of each request at the same time. Their
+    /// responses are recorded but not returned.
+    #[serde(default)]
+    pub mirror: Vec<UpstreamConfig>,
+}
+
+/// `forward` as written, spelled out so unknown keys are rejected, which
+/// `flatten` does not do.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ForwardFields {
+    target: String,
+    #[serde(default)]
+    preserve_host: bool,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    mirror: Vec<UpstreamConfig>,
+}
+
+impl From<ForwardFields> for ForwardConfig {
+    fn from(fields: ForwardFields) -> Self {
+        ForwardConfig {
+            upstream: UpstreamConfig {
+                target: fields.target,
+                preserve_host: fields.preserve_host,
+                headers: fields.headers,
+            },
+            mirror: fields.mirror,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpstreamConfig {
+    /// Base URL; the request's path and query are appended to it.
+    pub target: String,
+    /// Send the original `Host` header instead of the target's.
+    #[serde(default)]
+    pub preserve_host: bool,
+    /// Headers to set on outgoing requests. An empty value removes the
+    /// header instead.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// A scripted series of responses, one per request, used instead of the
+/// route's `response`.
+///
+/// ```yaml
+/// sequence:
+///   mode: repeat_last        # or cycle
+///   responses:
+///     - status: 500
+///     - status: 500
+///     - status: 200
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SequenceConfig {
+    #[serde(default)]
+    pub mode: SequenceMode,
+    pub responses: Vec<ResponseConfig>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequenceMode {
+    /// Keep returning the last response once the sequence is exhausted.
+    #[default]
+    RepeatLast,
+    /// Start over from the first response.
+    Cycle,
+}
+
+impl RouteConfig {
+    /// Short label for the route, e.g. `routes[2] POST /hooks/:id`.
+    pub fn describe(&self, index: usize) -> String {
+        format!(
+            "routes[{}] {} {}",
+            index,
+            self.method.as_deref().unwrap_or("ANY"),
+            self.path
+        )
+    }
+}
+
+impl SequenceConfig {
+    /// The response for the `step`th request (counting from zero).
+    pub fn response_at(&self, step: usize) -> Option<&ResponseConfig> {
+        let len = self.responses.len();
+        if len == 0 {
+            return None;
+        }
+        let index = match self.mode {
+            SequenceMode::RepeatLast => step.min(len - 1),
+            SequenceMode::Cycle => step % len,
+        };
+        self.responses.get(index)
+    }
+}
+
+/// A matched route together with the path parameters it captured.
+#[derive(Debug)]
+pub struct RouteMatch<'a> {
+    /// Position of the route in `Config::routes`.
+    pub index: usize,
+    pub route: &'a RouteConfig,
+    pub params: HashMap<String, String>,
+}
+
+/// Where captured requests are sent when replayed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayConfig {
+    /// Base URL, e.g. `http://localhost:3000`. The request's path and query
+    /// are appended to it.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Send the original `Host` header instead of the target's.
+    #[serde(default)]
+    pub preserve_host: bool,
+    /// Headers to set on replayed requests. An empty value removes the
+    /// header instead.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+impl ReplayConfig {
+    /// Where to send replayed requests, if a target is set.
+    pub fn upstream(&self) -> Option<UpstreamConfig> {
+        Some(UpstreamConfig {
+            target: self.target.clone()?,
+            preserve_host: self.preserve_host,
+            headers: self.headers.clone(),
+        })
+    }
+}
+
+/// The active config, swapped wholesale when the config file changes.
+pub type SharedConfig = Arc<RwLock<Arc<Config>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     #[serde(default = "default_port")]
     pub port: u16,
@@ -49,8 +415,19 @@ pub struct Config {
     pub host: String,
     #[serde(default)]
     pub response: ResponseConfig,
+    /// Delay applied to every response that does not set its own.
+    #[serde(default)]
+    pub delay: Option<DelayConfig>,
+    /// Faults injected into every response that does not set its own.
+    #[serde(default)]
+    pub chaos: Vec<FaultConfig>,
     #[serde(default)]
     pub routes: Vec<RouteConfig>,
+    /// Upstream for requests no route matches, instead of `response`.
+    #[serde(default)]
+    pub forward: Option<ForwardConfig>,
+    #[serde(default)]
+    pub replay: ReplayConfig,
 }
 
 fn default_port() -> u16 {
@@ -67,21 +444,45 @@ impl Default for Config {
             port: 9080,
             host: "127.0.0.1".to_string(),
             response: ResponseConfig::default(),
+            delay: None,
+            chaos: Vec::new(),
             routes: vec![RouteConfig {
-                path: "/health".to_string(),
+                path: PathPattern::parse("/health").expect("valid default route"),
                 method: Some("GET".to_string()),
+                conditions: MatchConditions::default(),
+                responder: None,
+                sequence: None,
+                forward: None,
+                signature: None,
+                cloudevents: None,
                 response: ResponseConfig {
                     status: 200,
                     headers: HashMap::new(),
                     body: r#"{"healthy": true}"#.to_string(),
+                    delay: None,
+                    mode: ResponseMode::Respond,
+                    chaos: Vec::new(),
                 },
             }],
+            forward: None,
+            replay: ReplayConfig::default(),
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Self {
+    /// Load `path` if given, else the first config file found, or the
+    /// defaults when there is none. Returns the path of the file that was
+    /// loaded, if any.
+    pub fn load(path: Option<&Path>) -> Result<(Self, Option<PathBuf>), ConfigError> {
+        match path.map(Path::to_path_buf).or_else(Self::locate) {
+            Some(path) => Ok((Self::from_file(&path)?, Some(path))),
+            None => Ok((Config::default(), None)),
+        }
+    }
+
+    /// The first config file that exists among the usual locations.
+    pub fn locate() -> Option<PathBuf> {
         // Try loading from multiple locations
         let config_paths = vec![
             PathBuf::from("./config.yaml"),
@@ -91,37 +492,320 @@ impl Config {
                 .unwrap_or_default(),
         ];
 
-        for path in config_paths {
-            if path.exists() {
-                if let Ok(contents) = fs::read_to_string(&path) {
-                    if let Ok(config) = serde_yaml::from_str::<Config>(&contents) {
-                        return config;
-                    }
+        config_paths.into_iter().find(|path| path.exists())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let config: Config =
+            serde_yaml::from_str(&contents).map_err(|source| ConfigError::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(ConfigError::Invalid {
+                path: path.to_path_buf(),
+                problems,
+            });
+        }
+
+        Ok(config)
+    }
+
+    /// Check the things serde cannot: status codes, header names, templates,
+    /// delay ranges and chaos percentages. Each problem is prefixed with the
+    /// location of the offending value, e.g. `routes[2].response.status`.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        validate_response("response", &self.response, &mut problems);
+        if let Some(delay) = &self.delay {
+            validate_delay("delay", delay, &mut problems);
+        }
+        validate_chaos("chaos", &self.chaos, &mut problems);
+        if let Some(forward) = &self.forward {
+            validate_forward("forward", forward, &mut problems);
+        }
+        validate_replay(&self.replay, &mut problems);
+
+        for (i, route) in self.routes.iter().enumerate() {
+            let at = format!("routes[{}]", i);
+
+            if let Some(method) = &route.method {
+                if Method::from_bytes(method.as_bytes()).is_err() {
+                    problems.push(format!("{}.method: invalid HTTP method {:?}", at, method));
+                }
+            }
+
+            validate_response(&format!("{}.response", at), &route.response, &mut problems);
+            if let Some(forward) = &route.forward {
+                validate_forward(&format!("{}.forward", at), forward, &mut problems);
+            }
+            if route.responder == Some(Responder::Discord)
+                && route
+                    .signature
+                    .as_ref()
+                    .is_none_or(|s| s.scheme != SignatureScheme::Discord)
+            {
+                problems.push(format!(
+                    "{}.responder: discord needs a `discord` signature check on the route",
+                    at
+                ));
+            }
+            if let Some(signature) = &route.signature {
+                if let Err(e) = signature.key() {
+                    let field = match signature.scheme {
+                        SignatureScheme::Discord => "public_key",
+                        _ => "secret",
+                    };
+                    problems.push(format!("{}.signature.{}: {}", at, field, e));
+                }
+            }
+            if let Some(cloudevents) = &route.cloudevents {
+                if cloudevents.require && !cloudevents.reject {
+                    problems.push(format!(
+                        "{}.cloudevents.require: has no effect without reject",
+                        at
+                    ));
+                }
+            }
+
+            if let Some(sequence) = &route.sequence {
+                if sequence.responses.is_empty() {
+                    problems.push(format!("{}.sequence.responses: must not be empty", at));
+                }
+                for (j, response) in sequence.responses.iter().enumerate() {
+                    validate_response(
+                        &format!("{}.sequence.responses[{}]", at, j),
+                        response,
+                        &mut problems,
+                    );
                 }
             }
         }
 
-        // Return default config
-        Config::default()
+        problems
     }
 
-    pub fn find_route(&self, method: &str, path: &str) -> Option<&RouteConfig> {
-        self.routes.iter().find(|r| {
-            r.path == path
-                && r.method
+    /// Find the route for a request. When several routes match, the most
+    /// specific path wins (exact, then `:param`, then `*wildcard`, then
+    /// regex); among equally specific paths, the first route listed whose
+    /// `match` conditions all hold wins.
+    pub fn find_route(&self, request: &CapturedRequest) -> Option<RouteMatch<'_>> {
+        let json = request.json_body();
+
+        self.routes
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| {
+                r.method
                     .as_ref()
-                    .map(|m| m.eq_ignore_ascii_case(method))
+                    .map(|m| m.eq_ignore_ascii_case(&request.method))
                     .unwrap_or(true)
-        })
+            })
+            .filter_map(|(index, route)| {
+                route.path.matches(&request.path).map(|params| RouteMatch {
+                    index,
+                    route,
+                    params,
+                })
+            })
+            .filter(|m| m.route.conditions.matches(request, json.as_ref()))
+            .max_by_key(|m| (m.route.path.specificity(), Reverse(m.index)))
     }
 
-    pub fn get_response(&self, method: &str, path: &str) -> &ResponseConfig {
-        self.find_route(method, path)
-            .map(|r| &r.response)
-            .unwrap_or(&self.response)
+    /// Layer command-line values on top of the file values.
+    pub fn apply_overrides(&mut self, overrides: &ConfigOverrides) {
+        if let Some(port) = overrides.port {
+            self.port = port;
+        }
+        if let Some(host) = &overrides.host {
+            self.host = host.clone();
+        }
+        if let Some(target) = &overrides.replay_target {
+            self.replay.target = Some(target.clone());
+        }
     }
 
     pub fn address(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
 }
+
+/// Settings given on the command line, which win over the config file.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub port: Option<u16>,
+    pub host: Option<String>,
+    pub replay_target: Option<String>,
+}
+
+fn validate_forward(at: &str, forward: &ForwardConfig, problems: &mut Vec<String>) {
+    validate_upstream(at, &forward.upstream, problems);
+    for (i, mirror) in forward.mirror.iter().enumerate() {
+        validate_upstream(&format!("{}.mirror[{}]", at, i), mirror, problems);
+    }
+}
+
+fn validate_upstream(at: &str, upstream: &UpstreamConfig, problems: &mut Vec<String>) {
+    if let Err(e) = upstream::parse_target(&upstream.target) {
+        problems.push(format!("{}.target: {}", at, e));
+    }
+    validate_header_names(
+        &format!("{}.headers", at),
+        upstream.headers.keys(),
+        problems,
+    );
+}
+
+fn validate_replay(replay: &ReplayConfig, problems: &mut Vec<String>) {
+    if let Some(target) = &replay.target {
+        if let Err(e) = upstream::parse_target(target) {
+            problems.push(format!("replay.target: {}", e));
+        }
+    }
+    validate_header_names("replay.headers", replay.headers.keys(), problems);
+}
+
+fn validate_header_names<'a>(
+    at: &str,
+    names: impl Iterator<Item = &'a String>,
+    problems: &mut Vec<String>,
+) {
+    for name in names {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            problems.push(format!("{}: invalid header name {:?}", at, name));
+        }
+    }
+}
+
+fn validate_response(at: &str, response: &ResponseConfig, problems: &mut Vec<String>) {
+    if StatusCode::from_u16(response.status).is_err() {
+        problems.push(format!(
+            "{}.status: {} is not a valid HTTP status",
+            at, response.status
+        ));
+    }
+
+    for (name, value) in &response.headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            problems.push(format!("{}.headers: invalid header name {:?}", at, name));
+        }
+        if let Err(e) = template::validate(value) {
+            problems.push(format!("{}.headers.{}: {}", at, name, e));
+        }
+    }
+
+    if let Err(e) = template::validate(&response.body) {
+        problems.push(format!("{}.body: {}", at, e));
+    }
+
+    if let Some(delay) = &response.delay {
+        validate_delay(&format!("{}.delay", at), delay, problems);
+    }
+
+    validate_chaos(&format!("{}.chaos", at), &response.chaos, problems);
+}
+
+fn validate_delay(at: &str, delay: &DelayConfig, problems: &mut Vec<String>) {
+    match *delay {
+        DelayConfig::Fixed(_) => {}
+        DelayConfig::Range { min, max } => {
+            if min > max {
+                problems.push(format!(
+                    "{}: min ({}) is greater than max ({})",
+                    at, min, max
+                ));
+            }
+        }
+        DelayConfig::Distribution { mean, stddev, .. } => {
+            if mean < 0.0 || stddev < 0.0 {
+                problems.push(format!("{}: mean and stddev must not be negative", at));
+            }
+        }
+    }
+}
+
+fn validate_chaos(at: &str, faults: &[FaultConfig], problems: &mut Vec<String>) {
+    for (i, fault) in faults.iter().enumerate() {
+        if !(0.0..=100.0).contains(&fault.percent) {
+            problems.push(format!(
+                "{}[{}].percent: {} is not between 0 and 100",
+                at, i, fault.percent
+            ));
+        }
+        if let Fault::Error { status, .. } = fault.fault {
+            if StatusCode::from_u16(status).is_err() {
+                problems.push(format!(
+                    "{}[{}].status: {} is not a valid HTTP status",
+                    at, i, status
+                ));
+            }
+        }
+    }
+
+    let total: f64 = faults.iter().map(|f| f.percent).sum();
+    if total > 100.0 {
+        problems.push(format!(
+            "{}: percentages add up to {}, more than 100",
+            at, total
+        ));
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: serde_yaml::Error,
+    },
+    Invalid {
+        path: PathBuf,
+        problems: Vec<String>,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, source } => match source.location() {
+                Some(location) => {
+                    // serde_yaml appends the location to the message; show it up front instead
+                    let suffix =
+                        format!(" at line {} column {}", location.line(), location.column());
+                    write!(
+                        f,
+                        "{}:{}:{}: {}",
+                        path.display(),
+                        location.line(),
+                        location.column(),
+                        source.to_string().replacen(&suffix, "", 1)
+                    )
+                }
+                None => write!(f, "{}: {}", path.display(), source),
+            },
+            ConfigError::Invalid { path, problems } => {
+                write!(f, "{}: invalid config", path.display())?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
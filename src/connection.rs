@@ -0,0 +1,360 @@
+use axum::Router;
+use hyper::{body::Incoming, service::service_fn, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use std::collections::VecDeque;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Notify;
+use tower::ServiceExt;
+
+use crate::request::Headers;
+
+/// Handle on the TCP connection a request arrived on, available to handlers
+/// as a request extension. It lets a handler drop the connection without
+/// sending a response.
+#[derive(Clone)]
+pub struct Connection {
+    /// A duplicate of the accepted socket, so it can be inspected while
+    /// hyper owns the original.
+    socket: Arc<TcpStream>,
+    abort: Arc<Notify>,
+    remote_addr: SocketAddr,
+    local_addr: SocketAddr,
+}
+
+impl Connection {
+    fn new(stream: &TcpStream) -> io::Result<Self> {
+        let socket: std::net::TcpStream = socket2::SockRef::from(stream).try_clone()?.into();
+        Ok(Self {
+            socket: Arc::new(TcpStream::from_std(socket)?),
+            abort: Arc::new(Notify::new()),
+            remote_addr: stream.peer_addr()?,
+            local_addr: stream.local_addr()?,
+        })
+    }
+
+    /// Address of the client, or of the last proxy in front of it.
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// Address of the listener the connection was accepted on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Close the connection immediately. Any in-flight response on it,
+    /// including other HTTP/2 streams, is abandoned.
+    pub fn close(&self) {
+        self.abort.notify_one();
+    }
+
+    /// Close the connection with a TCP reset instead of an orderly shutdown.
+    pub fn reset(&self) {
+        let _ = socket2::SockRef::from(self.socket.as_ref()).set_linger(Some(Duration::ZERO));
+        self.close();
+    }
+
+    /// Write bytes straight to the socket, bypassing hyper. Only meaningful
+    /// on HTTP/1 connections, and only before closing the connection.
+    pub async fn write_raw(&self, mut bytes: &[u8]) -> io::Result<()> {
+        while !bytes.is_empty() {
+            self.socket.writable().await?;
+            match self.socket.try_write(bytes) {
+                Ok(n) => bytes = &bytes[n..],
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait until the client closes its side of the connection.
+    pub async fn closed(&self) {
+        let mut buf = [0u8; 1];
+        loop {
+            match self.socket.peek(&mut buf).await {
+                Ok(0) | Err(_) => return,
+                // Pipelined data is waiting; check again later without consuming it
+                Ok(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+            }
+        }
+    }
+}
+
+/// Serve `router` on `listener`, attaching a [`Connection`] to every request.
+pub async fn serve(listener: TcpListener, router: Router) -> io::Result<()> {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            // Running out of file descriptors or similar; back off and keep serving
+            Err(_) => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+
+        let router = router.clone();
+        tokio::spawn(async move {
+            let _ = serve_connection(stream, router).await;
+        });
+    }
+}
+
+async fn serve_connection(stream: TcpStream, router: Router) -> io::Result<()> {
+    let connection = Connection::new(&stream)?;
+    let abort = connection.abort.clone();
+    let heads = Arc::new(Mutex::new(HeadReader::default()));
+    let stream = Recorded {
+        stream,
+        heads: heads.clone(),
+    };
+
+    let service = service_fn(move |mut request: Request<Incoming>| {
+        request.extensions_mut().insert(connection.clone());
+        // hyper reads a request's head before calling us with it
+        let received = heads.lock().unwrap().take(&request);
+        if let Some(headers) = received {
+            request.extensions_mut().insert(ReceivedHeaders(headers));
+        }
+        router.clone().oneshot(request)
+    });
+
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection(TokioIo::new(stream), service);
+
+    // Dropping the hyper connection closes the socket without a response
+    tokio::select! {
+        biased;
+        _ = abort.notified() => {}
+        _ = conn => {}
+    }
+
+    Ok(())
+}
+
+/// A request's headers exactly as they came off the wire: in their original
+/// order, with repeats where they were sent and names in the client's case.
+/// hyper's `HeaderMap` keeps none of that. Only HTTP/1 heads are read, so
+/// HTTP/2 requests carry no such extension.
+#[derive(Clone)]
+pub struct ReceivedHeaders(pub Headers);
+
+/// The accepted stream, letting a [`HeadReader`] see everything hyper reads.
+struct Recorded {
+    stream: TcpStream,
+    heads: Arc<Mutex<HeadReader>>,
+}
+
+impl AsyncRead for Recorded {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let start = buf.filled().len();
+        let poll = Pin::new(&mut self.stream).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            self.heads.lock().unwrap().feed(&buf.filled()[start..]);
+        }
+        poll
+    }
+}
+
+impl AsyncWrite for Recorded {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// More than hyper accepts, so any head hyper takes can be read here too.
+const MAX_HEADERS: usize = 128;
+const MAX_HEAD_LEN: usize = 1 << 20;
+
+/// Picks the request heads out of the bytes read on an HTTP/1 connection,
+/// stepping over bodies by their framing. Anything it does not understand,
+/// including HTTP/2, makes it give up for the rest of the connection.
+#[derive(Default)]
+struct HeadReader {
+    /// Bytes read but not yet consumed.
+    buf: Vec<u8>,
+    state: ReadState,
+    heads: VecDeque<Head>,
+}
+
+struct Head {
+    method: String,
+    target: String,
+    headers: Headers,
+}
+
+#[derive(Default)]
+enum ReadState {
+    #[default]
+    Head,
+    /// Bytes left of a body with a `Content-Length`.
+    Body(u64),
+    ChunkSize,
+    /// Bytes left of a chunk, including the CRLF after it.
+    ChunkData(u64),
+    Trailers,
+    Stopped,
+}
+
+impl HeadReader {
+    fn feed(&mut self, bytes: &[u8]) {
+        if matches!(self.state, ReadState::Stopped) {
+            return;
+        }
+        self.buf.extend_from_slice(bytes);
+
+        let mut pos = 0;
+        while pos < self.buf.len() {
+            let rest = &self.buf[pos..];
+            match self.state {
+                // Clients may send blank lines between requests
+                ReadState::Head if rest.starts_with(b"\r\n") => pos += 2,
+                ReadState::Head if rest.starts_with(b"\n") => pos += 1,
+                ReadState::Head => {
+                    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+                    let mut request = httparse::Request::new(&mut headers);
+                    match request.parse(rest) {
+                        Ok(httparse::Status::Complete(len)) => {
+                            let head = Head {
+                                method: request.method.unwrap_or_default().to_string(),
+                                target: request.path.unwrap_or_default().to_string(),
+                                headers: request
+                                    .headers
+                                    .iter()
+                                    .map(|h| (h.name, h.value))
+                                    .collect(),
+                            };
+                            self.state = body_state(&head);
+                            self.heads.push_back(head);
+                            pos += len;
+                        }
+                        Ok(httparse::Status::Partial) if rest.len() <= MAX_HEAD_LEN => break,
+                        _ => self.state = ReadState::Stopped,
+                    }
+                }
+                ReadState::Body(left) => {
+                    let taken = left.min(rest.len() as u64);
+                    pos += taken as usize;
+                    self.state = if taken == left {
+                        ReadState::Head
+                    } else {
+                        ReadState::Body(left - taken)
+                    };
+                }
+                ReadState::ChunkData(left) => {
+                    let taken = left.min(rest.len() as u64);
+                    pos += taken as usize;
+                    self.state = if taken == left {
+                        ReadState::ChunkSize
+                    } else {
+                        ReadState::ChunkData(left - taken)
+                    };
+                }
+                ReadState::ChunkSize => match httparse::parse_chunk_size(rest) {
+                    Ok(httparse::Status::Complete((len, 0))) => {
+                        pos += len;
+                        self.state = ReadState::Trailers;
+                    }
+                    Ok(httparse::Status::Complete((len, size))) => {
+                        pos += len;
+                        self.state = ReadState::ChunkData(size.saturating_add(2));
+                    }
+                    Ok(httparse::Status::Partial) => break,
+                    Err(_) => self.state = ReadState::Stopped,
+                },
+                ReadState::Trailers => match rest.windows(2).position(|w| w == b"\r\n") {
+                    Some(0) => {
+                        pos += 2;
+                        self.state = ReadState::Head;
+                    }
+                    Some(end) => pos += end + 2,
+                    None => break,
+                },
+                ReadState::Stopped => break,
+            }
+        }
+
+        if matches!(self.state, ReadState::Stopped) {
+            self.buf = Vec::new();
+        } else {
+            self.buf.drain(..pos);
+        }
+    }
+
+    /// The headers of `request` as received, if its head was read. Heads
+    /// come in the order hyper handles the requests, so any before it that
+    /// do not match were never handed on and are skipped.
+    fn take<B>(&mut self, request: &Request<B>) -> Option<Headers> {
+        while let Some(head) = self.heads.pop_front() {
+            if head.method == request.method().as_str() && request.uri() == head.target.as_str() {
+                return Some(head.headers);
+            }
+        }
+        None
+    }
+}
+
+/// How the body after `head` is framed, following RFC 9112 section 6.3.
+fn body_state(head: &Head) -> ReadState {
+    if head.method == "CONNECT" {
+        return ReadState::Stopped;
+    }
+    if let Some(codings) = head.headers.combined("transfer-encoding") {
+        let chunked = codings
+            .rsplit(',')
+            .next()
+            .is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked"));
+        return if chunked {
+            ReadState::ChunkSize
+        } else {
+            ReadState::Stopped
+        };
+    }
+    match head
+        .headers
+        .get("content-length")
+        .map(|v| v.trim().parse::<u64>())
+    {
+        None | Some(Ok(0)) => ReadState::Head,
+        Some(Ok(len)) => ReadState::Body(len),
+        Some(Err(_)) => ReadState::Stopped,
+    }
+}
@@ -0,0 +1,48 @@
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use std::io::Read;
+
+/// Upper bound on decoded output, so a compression bomb cannot exhaust memory.
+const MAX_DECODED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Undo every coding listed in a `Content-Encoding` header value.
+///
+/// Codings are listed in the order they were applied, so they are removed
+/// from last to first.
+pub fn decode(content_encoding: &str, bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut data = bytes.to_vec();
+
+    for coding in content_encoding.split(',').rev() {
+        let coding = coding.trim().to_ascii_lowercase();
+        data = match coding.as_str() {
+            "" | "identity" => data,
+            "gzip" | "x-gzip" => read_all(GzDecoder::new(data.as_slice()))?,
+            "deflate" => {
+                // "deflate" should be zlib-wrapped, but plenty of senders use raw deflate
+                read_all(ZlibDecoder::new(data.as_slice()))
+                    .or_else(|_| read_all(DeflateDecoder::new(data.as_slice())))?
+            }
+            "br" => read_all(brotli::Decompressor::new(data.as_slice(), 4096))?,
+            "zstd" => read_all(zstd::Decoder::new(data.as_slice()).map_err(|e| e.to_string())?)?,
+            other => return Err(format!("unsupported content-encoding: {}", other)),
+        };
+    }
+
+    Ok(data)
+}
+
+fn read_all<R: Read>(reader: R) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    reader
+        .take(MAX_DECODED_SIZE + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+
+    if out.len() as u64 > MAX_DECODED_SIZE {
+        return Err(format!(
+            "decoded body exceeds {} MiB",
+            MAX_DECODED_SIZE / 1024 / 1024
+        ));
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,17 @@
+use crate::request::{CapturedRequest, UpstreamResult};
+
+/// Events sent from the server side to the UI.
+#[derive(Debug)]
+pub enum AppEvent {
+    Request(Box<CapturedRequest>),
+    /// A request seen earlier, updated once it has been answered or dropped.
+    Responded(Box<CapturedRequest>),
+    /// The result of sending a copy of the request with the given id to a
+    /// mirror upstream.
+    Mirrored(u64, Box<UpstreamResult>),
+    /// The result of replaying the request with the given id.
+    Replayed(u64, Box<UpstreamResult>),
+    ConfigReloaded(String),
+    ConfigError(String),
+    StorageError(String),
+}
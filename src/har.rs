@@ -0,0 +1,159 @@
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::SecondsFormat;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::request::{CapturedRequest, Headers, RequestBody};
+
+/// Build a HAR 1.2 log of `requests`, oldest first, for browser devtools
+/// and other HTTP analyzers.
+pub fn to_har(requests: &[&CapturedRequest]) -> Value {
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "entries": requests.iter().map(|r| entry(r)).collect::<Vec<_>>(),
+        }
+    })
+}
+
+fn entry(request: &CapturedRequest) -> Value {
+    let wait = request.response.as_ref().map_or(0.0, |r| r.duration_ms);
+
+    let mut value = json!({
+        "startedDateTime": request.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "time": wait,
+        "request": har_request(request),
+        "response": har_response(request),
+        "cache": {},
+        // The request body has been read by the time timing starts, so only
+        // the wait for the response is known
+        "timings": {
+            "send": 0,
+            "wait": wait,
+            "receive": 0,
+        },
+    });
+
+    if let Some(connection) = &request.connection {
+        value["serverIPAddress"] = json!(connection.local_addr.ip().to_string());
+        value["connection"] = json!(connection.remote_addr.port().to_string());
+    }
+
+    value
+}
+
+/// Protocol the request arrived with; the response is sent over the same.
+fn http_version(request: &CapturedRequest) -> &str {
+    request
+        .connection
+        .as_ref()
+        .map_or("HTTP/1.1", |c| c.http_version.as_str())
+}
+
+fn har_request(request: &CapturedRequest) -> Value {
+    let host = request.header("host").unwrap_or("localhost");
+
+    let mut value = json!({
+        "method": request.method,
+        "url": format!("http://{}{}", host, request.full_path()),
+        "httpVersion": http_version(request),
+        "cookies": [],
+        "headers": request
+            .headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": Headers::text(value) }))
+            .collect::<Vec<_>>(),
+        "queryString": request
+            .query_params()
+            .into_iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect::<Vec<_>>(),
+        "headersSize": -1,
+        "bodySize": request.body.len(),
+    });
+
+    if !request.body.is_empty() {
+        let mut post_data = json!({
+            "mimeType": request.header("content-type").unwrap_or(""),
+        });
+        add_text(&mut post_data, &request.body, "_encoding");
+        value["postData"] = post_data;
+    }
+
+    value
+}
+
+/// HAR has no way to say "no response"; status 0 is what browsers record
+/// for requests that failed.
+fn har_response(request: &CapturedRequest) -> Value {
+    let Some(response) = &request.response else {
+        return json!({
+            "status": 0,
+            "statusText": "",
+            "httpVersion": "",
+            "cookies": [],
+            "headers": [],
+            "content": { "size": 0, "mimeType": "" },
+            "redirectURL": "",
+            "headersSize": -1,
+            "bodySize": -1,
+            "_error": request.dropped.as_deref().unwrap_or("no response was sent"),
+        });
+    };
+
+    let header = |name: &str| response.headers.get(name).unwrap_or("");
+
+    let mut content = json!({
+        "size": response.body.len(),
+        "mimeType": header("content-type"),
+    });
+    add_text(&mut content, &response.body, "encoding");
+
+    json!({
+        "status": response.status,
+        "statusText": StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or(""),
+        "httpVersion": http_version(request),
+        "cookies": [],
+        "headers": response
+            .headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": Headers::text(value) }))
+            .collect::<Vec<_>>(),
+        "content": content,
+        "redirectURL": header("location"),
+        "headersSize": -1,
+        "bodySize": response.body.len(),
+    })
+}
+
+/// Set `text` on a postData or content object. Binary bodies are base64
+/// encoded; HAR only defines `encoding` for response content, so request
+/// bodies use the custom `_encoding` field.
+fn add_text(value: &mut Value, body: &RequestBody, encoding_field: &str) {
+    match body {
+        RequestBody::Empty => {}
+        RequestBody::Utf8(text) => value["text"] = json!(text),
+        RequestBody::Binary(bytes) => {
+            value["text"] = json!(BASE64.encode(bytes));
+            value[encoding_field] = json!("base64");
+        }
+    }
+}
+
+/// Write a HAR file of `requests`, oldest first.
+pub fn write(path: &Path, requests: &[&CapturedRequest]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut out, &to_har(requests))?;
+    writeln!(out)?;
+    out.flush()
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::cli::OutputFormat;
+use crate::event::AppEvent;
+use crate::request::CapturedRequest;
+
+/// Stream captured requests to `out` instead of running the TUI, until
+/// Ctrl-C or the server stops. Diagnostics go to stderr so `out` stays
+/// machine-readable. Returns the server's error if it stopped with one.
+///
+/// JSON lines are written once a request has been answered or dropped, so
+/// they carry the route, response and forwards. Mirror and replay results
+/// arrive later and are written as another copy of the request, which
+/// supersedes the earlier one as in session files. Text lines only name
+/// the request and are written as soon as it arrives. Requests are kept
+/// only until their last line is written.
+pub async fn run(
+    mut rx: mpsc::UnboundedReceiver<AppEvent>,
+    mut server: JoinHandle<io::Result<()>>,
+    format: OutputFormat,
+    mut out: Box<dyn Write + Send>,
+) -> io::Result<()> {
+    // Requests whose JSON line is still to be written or rewritten: those
+    // not yet answered, and those waiting for mirror results
+    let mut requests: BTreeMap<u64, CapturedRequest> = BTreeMap::new();
+    let mut stopped = Ok(());
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            result = &mut server => {
+                stopped = result.unwrap_or_else(|e| Err(io::Error::other(e)));
+                break;
+            }
+            event = rx.recv() => match event {
+                Some(AppEvent::Request(request)) => match format {
+                    OutputFormat::Text => write_request(&mut out, &request, format)?,
+                    OutputFormat::Jsonl => {
+                        requests.insert(request.id, *request);
+                    }
+                },
+                Some(AppEvent::Responded(request)) if format == OutputFormat::Jsonl => {
+                    let mut request = *request;
+                    if let Some(previous) = requests.remove(&request.id) {
+                        request.merge_results(&previous);
+                    }
+                    write_request(&mut out, &request, format)?;
+                    if request.pending_mirrors > 0 {
+                        requests.insert(request.id, request);
+                    }
+                }
+                Some(AppEvent::Mirrored(id, result)) if format == OutputFormat::Jsonl => {
+                    add_result(&mut out, &mut requests, id, |r| {
+                        r.forwards.push(*result);
+                        r.pending_mirrors = r.pending_mirrors.saturating_sub(1);
+                    })?
+                }
+                Some(AppEvent::Replayed(id, result)) if format == OutputFormat::Jsonl => {
+                    add_result(&mut out, &mut requests, id, |r| r.replays.push(*result))?
+                }
+                Some(AppEvent::ConfigReloaded(message)) => eprintln!("{}", message),
+                Some(AppEvent::ConfigError(message) | AppEvent::StorageError(message)) => {
+                    eprintln!("error: {}", message)
+                }
+                Some(_) => {}
+                None => break,
+            },
+        }
+    }
+
+    // Requests still waiting for their response, e.g. on a held connection
+    for request in requests.values().filter(|r| !is_settled(r)) {
+        write_request(&mut out, request, format)?;
+    }
+
+    out.flush()?;
+    stopped.map_err(|e| io::Error::new(e.kind(), format!("server stopped: {}", e)))
+}
+
+/// Record a late upstream result, and write the updated request again if
+/// it was already written. The request is forgotten once nothing more is
+/// expected for it.
+fn add_result(
+    out: &mut dyn Write,
+    requests: &mut BTreeMap<u64, CapturedRequest>,
+    id: u64,
+    add: impl FnOnce(&mut CapturedRequest),
+) -> io::Result<()> {
+    let Some(request) = requests.get_mut(&id) else {
+        return Ok(());
+    };
+    add(request);
+    if is_settled(request) {
+        write_request(out, request, OutputFormat::Jsonl)?;
+        if request.pending_mirrors == 0 {
+            requests.remove(&id);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the request has been answered or dropped, and so written out.
+fn is_settled(request: &CapturedRequest) -> bool {
+    request.response.is_some() || request.dropped.is_some()
+}
+
+fn write_request(
+    out: &mut dyn Write,
+    request: &CapturedRequest,
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Jsonl => {
+            serde_json::to_writer(&mut *out, request)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Text => writeln!(out, "{}", request)?,
+    }
+    // Flush per request so consumers such as `jq` or `tail -f` see it immediately
+    out.flush()
+}
@@ -0,0 +1,24 @@
+use chrono::Utc;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+static LOG_FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Send diagnostic messages to `path`, appending to it if it exists.
+pub fn init(path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let _ = LOG_FILE.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Append a timestamped line to the log file. Does nothing unless
+/// `--log` was given.
+pub fn write(message: impl AsRef<str>) {
+    if let Some(file) = LOG_FILE.get() {
+        if let Ok(mut file) = file.lock() {
+            let _ = writeln!(file, "{} {}", Utc::now().to_rfc3339(), message.as_ref());
+        }
+    }
+}
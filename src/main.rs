@@ -1,30 +1,303 @@
+mod chaos;
+mod cli;
+mod cloudevents;
 mod config;
+mod connection;
+mod encoding;
+mod event;
+mod har;
+mod headless;
+mod logger;
+mod matcher;
+mod provider;
 mod request;
+mod responder;
 mod server;
+mod signature;
+mod snippet;
+mod storage;
+mod template;
 mod ui;
+mod upstream;
+mod watcher;
 
+use clap::Parser;
+use cli::{Cli, Command, ConfigArgs, ExportArgs, ReplayArgs, ServeArgs};
 use config::Config;
+use std::fs::OpenOptions;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use storage::Session;
+use tokio::net::TcpListener;
 use tokio::sync::mpsc;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Load configuration
-    let config = Config::load();
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Check(args)) => check(&args),
+        Some(Command::Serve(args)) => serve(args).await,
+        Some(Command::Sessions) => list_sessions(),
+        Some(Command::Export(args)) => export(&args),
+        Some(Command::Replay(args)) => replay(args).await,
+        None => serve(cli.serve).await,
+    }
+}
+
+/// Load the config or exit with the error.
+fn load_config(args: &ConfigArgs) -> (Config, Option<PathBuf>) {
+    match Config::load(args.config.as_deref()) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn describe_source(path: &Option<PathBuf>) -> String {
+    path.as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "built-in defaults".to_string())
+}
+
+fn check(args: &ConfigArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, config_path) = load_config(args);
+    println!("{}: OK", describe_source(&config_path));
+    Ok(())
+}
+
+fn list_sessions() -> Result<(), Box<dyn std::error::Error>> {
+    let sessions = Session::list()?;
+    if sessions.is_empty() {
+        println!("No sessions in {}", Session::dir().display());
+        return Ok(());
+    }
+
+    for session in sessions {
+        let modified = session
+            .modified
+            .map(|m| m.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        println!(
+            "{:<24} {:>6} request{}  {}",
+            session.name,
+            session.requests,
+            if session.requests == 1 { " " } else { "s" },
+            modified
+        );
+    }
+    Ok(())
+}
+
+fn export(args: &ExportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let path = Session::path_for(&args.session);
+    let history = match Session::read(&path) {
+        Ok(history) => history,
+        Err(e) => {
+            eprintln!("error: cannot read session {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    let requests: Vec<_> = history
+        .requests
+        .iter()
+        .filter(|r| args.ids.is_empty() || args.ids.contains(&r.id))
+        .collect();
+
+    match &args.output {
+        Some(output) => {
+            har::write(output, &requests)?;
+            eprintln!(
+                "Exported {} request{} to {}",
+                requests.len(),
+                if requests.len() == 1 { "" } else { "s" },
+                output.display()
+            );
+        }
+        None => {
+            let mut out = io::stdout().lock();
+            serde_json::to_writer_pretty(&mut out, &har::to_har(&requests))?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+async fn replay(args: ReplayArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let (config, _) = load_config(&args.config);
+    let mut replay = config.replay;
+    if args.to.is_some() {
+        replay.target = args.to;
+    }
+    replay.preserve_host |= args.preserve_host;
+    replay.headers.extend(args.headers);
+
+    let Some(target) = replay.upstream() else {
+        eprintln!("error: no replay target; pass --to URL or set replay.target in the config");
+        std::process::exit(1);
+    };
+    if let Err(e) = upstream::parse_target(&target.target) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+
+    let path = Session::path_for(&args.session);
+    if !path.exists() {
+        eprintln!("error: no session at {}", path.display());
+        std::process::exit(1);
+    }
+    let (mut session, history) = match Session::open(&args.session) {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("error: cannot read session {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    };
+
+    // Results are saved back to the session, so they show up in the TUI
+    for request in history
+        .requests
+        .iter()
+        .filter(|r| args.ids.is_empty() || args.ids.contains(&r.id))
+    {
+        let result = upstream::send(request, &target).await;
+        let original = request
+            .response
+            .as_ref()
+            .map(|r| format!(" (originally {})", r.status))
+            .unwrap_or_default();
+        println!(
+            "#{} {} {} -> {}{}",
+            request.id,
+            request.method,
+            request.full_path(),
+            result.summary(),
+            original
+        );
+        session.append_replay(request.id, &result)?;
+    }
+    Ok(())
+}
+
+async fn serve(args: ServeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    // Load configuration, with command-line values on top
+    let (mut config, config_path) = load_config(&args.config);
+    let overrides = args.overrides();
+    if let Some(target) = &overrides.replay_target {
+        if let Err(e) = upstream::parse_target(target) {
+            eprintln!("error: --replay-to: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if args.check_config {
+        println!("{}: OK", describe_source(&config_path));
+        return Ok(());
+    }
+    config.apply_overrides(&overrides);
+
+    if let Some(path) = &args.log {
+        logger::init(path)?;
+    }
+
+    let config_source = describe_source(&config_path);
+    logger::write(format!("using config from {}", config_source));
+
     let listening_address = config.address();
+    let config = Arc::new(RwLock::new(Arc::new(config)));
 
     // Create channel for request communication
-    let (tx, rx) = mpsc::unbounded_channel();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let events = tx.clone();
+
+    // Record to the session between the server and the UI
+    let session = match &args.session {
+        Some(name) => {
+            let (session, history) = match Session::open(name) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    eprintln!(
+                        "error: cannot open session {}: {}",
+                        Session::path_for(name).display(),
+                        e
+                    );
+                    std::process::exit(1);
+                }
+            };
+            if let Some(last) = history.requests.iter().map(|r| r.id).max() {
+                server::resume_request_ids(last);
+            }
+            logger::write(format!(
+                "session {}: {} request(s) restored",
+                name,
+                history.requests.len()
+            ));
+
+            let (session_tx, session_rx) = mpsc::unbounded_channel();
+            tokio::spawn(storage::record(session, rx, session_tx));
+            rx = session_rx;
+            Some((name.clone(), history))
+        }
+        None => None,
+    };
 
     // Spawn HTTP server in background
     let server_config = config.clone();
-    tokio::spawn(async move {
-        if let Err(e) = server::run_server(server_config, tx).await {
-            eprintln!("Server error: {}", e);
+    // Bind before announcing anything, so a port in use is reported as such
+    let listener = match TcpListener::bind(&listening_address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("error: cannot listen on {}: {}", listening_address, e);
+            std::process::exit(1);
+        }
+    };
+    logger::write(format!("listening on {}", listening_address));
+    let server = tokio::spawn(async move {
+        let result = server::run_server(listener, server_config, config_path, overrides, tx).await;
+        if let Err(e) = &result {
+            logger::write(format!("server error: {}", e));
         }
+        result
     });
 
-    // Run TUI on main thread
-    ui::run_tui(listening_address, rx).await?;
+    if args.no_tui {
+        let out: Box<dyn Write + Send> = match &args.output {
+            Some(path) => Box::new(BufWriter::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => Box::new(io::stdout()),
+        };
+        eprintln!("Listening on {} ({})", listening_address, config_source);
+        if let Some((name, history)) = &session {
+            eprintln!(
+                "Recording to session {} ({} earlier request{})",
+                name,
+                history.requests.len(),
+                if history.requests.len() == 1 { "" } else { "s" }
+            );
+            if history.skipped > 0 {
+                eprintln!("warning: skipped {} unreadable line(s)", history.skipped);
+            }
+        }
+        if let Err(e) = headless::run(rx, server, args.format, out).await {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+    } else {
+        // Run TUI on main thread
+        ui::run_tui(
+            listening_address,
+            config_source,
+            session,
+            config,
+            events,
+            rx,
+        )
+        .await?;
+    }
 
     Ok(())
 }
@@ -0,0 +1,527 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::request::CapturedRequest;
+
+/// A route path, compiled once when the config is parsed.
+///
+/// - `/health` matches that exact path
+/// - `/orders/:id` captures one segment as `id`
+/// - `/files/*rest` captures everything after `/files/` as `rest`
+/// - `^/v(?P<version>\d+)/.*$` is a regular expression; named groups become params
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PathPattern {
+    source: String,
+    kind: PatternKind,
+}
+
+#[derive(Debug, Clone)]
+enum PatternKind {
+    Exact,
+    Segments(Vec<Segment>),
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+/// Ordering key for route precedence; larger is more specific.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Specificity {
+    kind: u8,
+    literals: usize,
+    no_wildcard: bool,
+    params: usize,
+}
+
+impl PathPattern {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if source.starts_with('^') {
+            let regex = Regex::new(source).map_err(|e| format!("invalid path regex: {}", e))?;
+            return Ok(Self {
+                source: source.to_string(),
+                kind: PatternKind::Regex(regex),
+            });
+        }
+
+        let raw_segments: Vec<&str> = source.split('/').collect();
+        let mut segments = Vec::with_capacity(raw_segments.len());
+
+        for (i, segment) in raw_segments.iter().enumerate() {
+            if let Some(name) = segment.strip_prefix(':') {
+                if name.is_empty() {
+                    return Err(format!("path parameter without a name in {:?}", source));
+                }
+                segments.push(Segment::Param(name.to_string()));
+            } else if let Some(name) = segment.strip_prefix('*') {
+                if i != raw_segments.len() - 1 {
+                    return Err(format!(
+                        "wildcard must be the last path segment in {:?}",
+                        source
+                    ));
+                }
+                segments.push(Segment::Wildcard(name.to_string()));
+            } else {
+                segments.push(Segment::Literal(segment.to_string()));
+            }
+        }
+
+        let kind = if segments.iter().all(|s| matches!(s, Segment::Literal(_))) {
+            PatternKind::Exact
+        } else {
+            PatternKind::Segments(segments)
+        };
+
+        Ok(Self {
+            source: source.to_string(),
+            kind,
+        })
+    }
+
+    /// Match a request path, returning the captured parameters on success.
+    pub fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        match &self.kind {
+            PatternKind::Exact => (self.source == path).then(HashMap::new),
+            PatternKind::Segments(segments) => match_segments(segments, path),
+            PatternKind::Regex(regex) => {
+                let captures = regex.captures(path)?;
+                Some(
+                    regex
+                        .capture_names()
+                        .flatten()
+                        .filter_map(|name| {
+                            captures
+                                .name(name)
+                                .map(|m| (name.to_string(), m.as_str().to_string()))
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        match &self.kind {
+            PatternKind::Exact => Specificity {
+                kind: 2,
+                literals: 0,
+                no_wildcard: true,
+                params: 0,
+            },
+            PatternKind::Segments(segments) => Specificity {
+                kind: 1,
+                literals: segments
+                    .iter()
+                    .filter(|s| matches!(s, Segment::Literal(_)))
+                    .count(),
+                no_wildcard: !segments.iter().any(|s| matches!(s, Segment::Wildcard(_))),
+                params: segments
+                    .iter()
+                    .filter(|s| matches!(s, Segment::Param(_)))
+                    .count(),
+            },
+            PatternKind::Regex(_) => Specificity {
+                kind: 0,
+                literals: 0,
+                no_wildcard: false,
+                params: 0,
+            },
+        }
+    }
+}
+
+fn match_segments(segments: &[Segment], path: &str) -> Option<HashMap<String, String>> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let mut params = HashMap::new();
+
+    for (i, segment) in segments.iter().enumerate() {
+        match segment {
+            Segment::Literal(literal) => {
+                if parts.get(i)? != literal {
+                    return None;
+                }
+            }
+            Segment::Param(name) => {
+                let part = parts.get(i).filter(|p| !p.is_empty())?;
+                params.insert(name.clone(), part.to_string());
+            }
+            Segment::Wildcard(name) => {
+                if i >= parts.len() {
+                    return None;
+                }
+                let name = if name.is_empty() { "*" } else { name };
+                params.insert(name.to_string(), parts[i..].join("/"));
+                return Some(params);
+            }
+        }
+    }
+
+    (parts.len() == segments.len()).then_some(params)
+}
+
+impl TryFrom<String> for PathPattern {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        PathPattern::parse(&source)
+    }
+}
+
+impl From<PathPattern> for String {
+    fn from(pattern: PathPattern) -> Self {
+        pattern.source
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Extra conditions a request must satisfy for a route to match. All
+/// conditions are AND-ed together.
+///
+/// Body keys are JSON Pointers (`/data/object/id`) or dotted paths in the
+/// style of JSONPath (`$.data.items[0].id`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MatchConditions {
+    #[serde(default)]
+    pub headers: HashMap<String, ValueMatcher>,
+    #[serde(default)]
+    pub query: HashMap<String, ValueMatcher>,
+    #[serde(default)]
+    pub body: HashMap<String, ValueMatcher>,
+}
+
+impl MatchConditions {
+    /// `json` is the request body parsed as JSON, if it is JSON at all.
+    pub fn matches(&self, request: &CapturedRequest, json: Option<&serde_json::Value>) -> bool {
+        let headers_match = self
+            .headers
+            .iter()
+            .all(|(name, matcher)| matcher.matches(request.header(name)));
+
+        let query_match = self.query.is_empty() || {
+            let params = request.query_params();
+            self.query.iter().all(|(name, matcher)| {
+                matcher.matches(
+                    params
+                        .iter()
+                        .find(|(k, _)| k == name)
+                        .map(|(_, v)| v.as_str()),
+                )
+            })
+        };
+
+        let body_match = self.body.iter().all(|(key, matcher)| {
+            let value = json.and_then(|j| j.pointer(&to_json_pointer(key)));
+            let text = value.map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            });
+            matcher.matches(text.as_deref())
+        });
+
+        headers_match && query_match && body_match
+    }
+}
+
+/// Convert a body field key to a JSON Pointer. Keys starting with `/` are
+/// already pointers; anything else is read as a dotted path such as
+/// `$.data.items[0].id` or `data.type`.
+fn to_json_pointer(key: &str) -> String {
+    if key.starts_with('/') {
+        return key.to_string();
+    }
+
+    let path = key.strip_prefix('$').unwrap_or(key);
+    let mut pointer = String::new();
+
+    for part in path.split('.').filter(|p| !p.is_empty()) {
+        let mut rest = part;
+        if let Some(bracket) = rest.find('[') {
+            push_pointer_token(&mut pointer, &rest[..bracket]);
+            rest = &rest[bracket..];
+            while let Some(index) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+                push_pointer_token(
+                    &mut pointer,
+                    index.0.trim_matches(|c| c == '\'' || c == '"'),
+                );
+                rest = index.1;
+            }
+        } else {
+            push_pointer_token(&mut pointer, rest);
+        }
+    }
+
+    pointer
+}
+
+fn push_pointer_token(pointer: &mut String, token: &str) {
+    if token.is_empty() {
+        return;
+    }
+    pointer.push('/');
+    pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
+
+/// Condition on a single header, query parameter or body field.
+///
+/// Written in YAML as a plain value for an exact match, or as one of
+/// `{ equals: ... }`, `{ regex: ... }` or `{ present: true|false }`.
+/// Unquoted numbers match by value, so `1.0` matches `1` and `1.0` alike.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "ValueMatcherDef", into = "ValueMatcherDef")]
+pub enum ValueMatcher {
+    Equals(String),
+    Number(serde_json::Number),
+    Regex(Regex),
+    Present(bool),
+}
+
+impl ValueMatcher {
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        match self {
+            ValueMatcher::Equals(expected) => value == Some(expected.as_str()),
+            ValueMatcher::Number(expected) => value
+                .and_then(|v| v.parse::<serde_json::Number>().ok())
+                .is_some_and(|v| same_number(&v, expected)),
+            ValueMatcher::Regex(regex) => value.is_some_and(|v| regex.is_match(v)),
+            ValueMatcher::Present(present) => value.is_some() == *present,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ValueMatcherDef {
+    Rule(MatchRule),
+    Literal(Scalar),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+enum MatchRule {
+    Equals(Scalar),
+    Regex(String),
+    Present(bool),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Scalar {
+    Bool(bool),
+    Int(i64),
+    /// Ids above `i64::MAX`, which would otherwise lose precision as floats.
+    Uint(u64),
+    Float(f64),
+    String(String),
+}
+
+impl From<Scalar> for ValueMatcher {
+    fn from(scalar: Scalar) -> Self {
+        match scalar {
+            Scalar::Bool(b) => ValueMatcher::Equals(b.to_string()),
+            Scalar::Int(i) => ValueMatcher::Number(i.into()),
+            Scalar::Uint(u) => ValueMatcher::Number(u.into()),
+            Scalar::Float(f) => match serde_json::Number::from_f64(f) {
+                Some(number) => ValueMatcher::Number(number),
+                None => ValueMatcher::Equals(f.to_string()),
+            },
+            Scalar::String(s) => ValueMatcher::Equals(s),
+        }
+    }
+}
+
+/// Integers compare exactly, so large ids keep their precision; anything
+/// else compares as floating point.
+fn same_number(a: &serde_json::Number, b: &serde_json::Number) -> bool {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a == b;
+    }
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        return a == b;
+    }
+    a.as_f64() == b.as_f64()
+}
+
+impl TryFrom<ValueMatcherDef> for ValueMatcher {
+    type Error = String;
+
+    fn try_from(def: ValueMatcherDef) -> Result<Self, Self::Error> {
+        Ok(match def {
+            ValueMatcherDef::Literal(value) | ValueMatcherDef::Rule(MatchRule::Equals(value)) => {
+                value.into()
+            }
+            ValueMatcherDef::Rule(MatchRule::Regex(pattern)) => ValueMatcher::Regex(
+                Regex::new(&pattern).map_err(|e| format!("invalid regex: {}", e))?,
+            ),
+            ValueMatcherDef::Rule(MatchRule::Present(present)) => ValueMatcher::Present(present),
+        })
+    }
+}
+
+impl From<ValueMatcher> for ValueMatcherDef {
+    fn from(matcher: ValueMatcher) -> Self {
+        match matcher {
+            ValueMatcher::Equals(value) => ValueMatcherDef::Literal(Scalar::String(value)),
+            ValueMatcher::Number(number) => {
+                ValueMatcherDef::Literal(match (number.as_i64(), number.as_u64()) {
+                    (Some(i), _) => Scalar::Int(i),
+                    (None, Some(u)) => Scalar::Uint(u),
+                    (None, None) => Scalar::Float(number.as_f64().unwrap_or_default()),
+                })
+            }
+            ValueMatcher::Regex(regex) => {
+                ValueMatcherDef::Rule(MatchRule::Regex(regex.as_str().to_string()))
+            }
+            ValueMatcher::Present(present) => ValueMatcherDef::Rule(MatchRule::Present(present)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(source: &str) -> PathPattern {
+        PathPattern::parse(source).unwrap()
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn exact_beats_segments_beats_regex() {
+        let exact = pattern("/orders/new").specificity();
+        let segments = pattern("/orders/:id").specificity();
+        let regex = pattern("^/orders/.*$").specificity();
+        assert!(exact > segments);
+        assert!(segments > regex);
+    }
+
+    #[test]
+    fn more_literals_beat_fewer() {
+        assert!(pattern("/orders/:id").specificity() > pattern("/:kind/:id").specificity());
+        assert!(
+            pattern("/orders/:id/items").specificity() > pattern("/orders/:id/:list").specificity()
+        );
+    }
+
+    #[test]
+    fn params_beat_a_wildcard() {
+        assert!(pattern("/files/:name").specificity() > pattern("/files/*rest").specificity());
+        assert!(
+            pattern("/files/:dir/:name").specificity() > pattern("/files/:dir/*").specificity()
+        );
+    }
+
+    #[test]
+    fn exact_path() {
+        assert_eq!(pattern("/health").matches("/health"), Some(params(&[])));
+        assert_eq!(pattern("/health").matches("/health/"), None);
+        assert_eq!(pattern("/health").matches("/healthz"), None);
+    }
+
+    #[test]
+    fn param_captures_one_segment() {
+        let orders = pattern("/orders/:id");
+        assert_eq!(orders.matches("/orders/42"), Some(params(&[("id", "42")])));
+        assert_eq!(orders.matches("/orders/42/items"), None);
+        assert_eq!(orders.matches("/orders/"), None);
+        assert_eq!(orders.matches("/orders"), None);
+
+        assert_eq!(
+            pattern("/:kind/:id/items").matches("/users/7/items"),
+            Some(params(&[("kind", "users"), ("id", "7")]))
+        );
+    }
+
+    #[test]
+    fn wildcard_captures_the_rest() {
+        let files = pattern("/files/*rest");
+        assert_eq!(
+            files.matches("/files/a/b.txt"),
+            Some(params(&[("rest", "a/b.txt")]))
+        );
+        assert_eq!(files.matches("/files/a"), Some(params(&[("rest", "a")])));
+        assert_eq!(files.matches("/files"), None);
+        assert_eq!(files.matches("/other/a"), None);
+
+        assert_eq!(
+            pattern("/files/*").matches("/files/a/b"),
+            Some(params(&[("*", "a/b")]))
+        );
+    }
+
+    #[test]
+    fn regex_named_groups_become_params() {
+        let versioned = pattern(r"^/v(?P<version>\d+)/.*$");
+        assert_eq!(
+            versioned.matches("/v2/users"),
+            Some(params(&[("version", "2")]))
+        );
+        assert_eq!(versioned.matches("/va/users"), None);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(PathPattern::parse("/orders/:").is_err());
+        assert!(PathPattern::parse("/files/*rest/more").is_err());
+        assert!(PathPattern::parse("^/(unclosed").is_err());
+    }
+
+    fn matcher(yaml: &str) -> ValueMatcher {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn numbers_match_by_value() {
+        assert!(matcher("1").matches(Some("1")));
+        assert!(matcher("1").matches(Some("1.0")));
+        assert!(matcher("1.0").matches(Some("1")));
+        assert!(matcher("1.5").matches(Some("1.50")));
+        assert!(matcher("-3").matches(Some("-3.0")));
+        assert!(matcher("{ equals: 1 }").matches(Some("1.0")));
+
+        assert!(!matcher("1").matches(Some("2")));
+        assert!(!matcher("1").matches(Some("one")));
+        assert!(!matcher("1").matches(Some("")));
+        assert!(!matcher("1").matches(None));
+    }
+
+    #[test]
+    fn quoted_numbers_match_as_text() {
+        assert!(matcher("'1'").matches(Some("1")));
+        assert!(!matcher("'1'").matches(Some("1.0")));
+    }
+
+    #[test]
+    fn large_ids_keep_their_precision() {
+        // 2^53 + 1, the first integer an f64 cannot hold
+        let id = matcher("9007199254740993");
+        assert!(id.matches(Some("9007199254740993")));
+        assert!(!id.matches(Some("9007199254740992")));
+
+        let min = matcher("-9223372036854775808");
+        assert!(min.matches(Some("-9223372036854775808")));
+        assert!(!min.matches(Some("-9223372036854775807")));
+
+        let max = matcher("18446744073709551615");
+        assert!(max.matches(Some("18446744073709551615")));
+        assert!(!max.matches(Some("18446744073709551614")));
+    }
+}
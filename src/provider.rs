@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::cloudevents::CloudEvents;
+use crate::request::CapturedRequest;
+
+/// A webhook recognized as coming from a well-known provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderEvent {
+    /// Lower-case provider name, e.g. `github`.
+    pub provider: String,
+    /// Event type in the provider's own terms, e.g. `push` or `invoice.paid`.
+    pub event: Option<String>,
+    /// The provider's id for this delivery, which stays the same on retries.
+    pub delivery: Option<String>,
+}
+
+impl ProviderEvent {
+    fn new(provider: &str, event: Option<String>, delivery: Option<String>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            event: event.filter(|e| !e.is_empty()),
+            delivery: delivery.filter(|d| !d.is_empty()),
+        }
+    }
+
+    /// Short label for the request list, e.g. `stripe invoice.paid`.
+    pub fn label(&self) -> String {
+        match &self.event {
+            Some(event) => format!("{} {}", self.provider, event),
+            None => self.provider.clone(),
+        }
+    }
+}
+
+/// Recognize the provider that sent `request` from its headers and body.
+/// Providers that announce themselves in headers are checked before those
+/// only recognizable by their payload.
+pub fn detect(request: &CapturedRequest) -> Option<ProviderEvent> {
+    let json = request.json_body();
+    let json = json.as_ref();
+    let header = |name: &str| request.header(name).map(str::to_string);
+    let field = |name: &str| string_at(json, &[name]);
+
+    if let Some(event) = header("x-github-event") {
+        // Most events carry an action, e.g. `pull_request` + `opened`
+        let event = match field("action") {
+            Some(action) => format!("{}.{}", event, action),
+            None => event,
+        };
+        return Some(ProviderEvent::new(
+            "github",
+            Some(event),
+            header("x-github-delivery"),
+        ));
+    }
+
+    if let Some(event) = header("x-gitlab-event") {
+        return Some(ProviderEvent::new(
+            "gitlab",
+            field("object_kind").or(Some(event)),
+            header("x-gitlab-event-uuid").or_else(|| header("x-gitlab-webhook-uuid")),
+        ));
+    }
+
+    if let Some(topic) = header("x-shopify-topic") {
+        return Some(ProviderEvent::new(
+            "shopify",
+            Some(topic),
+            header("x-shopify-webhook-id").or_else(|| header("x-shopify-event-id")),
+        ));
+    }
+
+    if request.header("x-slack-signature").is_some() {
+        return Some(slack(request, json));
+    }
+
+    if request.header("stripe-signature").is_some()
+        || (field("object").as_deref() == Some("event")
+            && field("id").is_some_and(|id| id.starts_with("evt_")))
+    {
+        return Some(ProviderEvent::new("stripe", field("type"), field("id")));
+    }
+
+    if let Some(events) = json.and_then(sendgrid_events) {
+        return Some(sendgrid(events));
+    }
+
+    if request.header("x-twilio-signature").is_some() {
+        return Some(twilio(request));
+    }
+
+    if let Some(cloudevents) = &request.cloudevents {
+        return Some(cloudevent(cloudevents));
+    }
+
+    None
+}
+
+/// Events API callbacks are JSON; slash commands and interactive
+/// components arrive as forms.
+fn slack(request: &CapturedRequest, json: Option<&Value>) -> ProviderEvent {
+    if json.is_some() {
+        return ProviderEvent::new(
+            "slack",
+            string_at(json, &["event", "type"]).or_else(|| string_at(json, &["type"])),
+            string_at(json, &["event_id"]),
+        );
+    }
+
+    let form = request.form_params();
+    let param = |name: &str| form_param(&form, name);
+    let event = match param("command") {
+        Some(command) => Some(format!("command {}", command)),
+        None => param("payload")
+            .and_then(|p| serde_json::from_str::<Value>(&p).ok())
+            .and_then(|p| string_at(Some(&p), &["type"])),
+    };
+    ProviderEvent::new("slack", event, param("trigger_id"))
+}
+
+/// The Event Webhook posts a batch of events as a JSON array.
+fn sendgrid_events(json: &Value) -> Option<&Vec<Value>> {
+    let events = json.as_array()?;
+    let first = events.first()?;
+    (first.get("sg_event_id").is_some() || first.get("sg_message_id").is_some()).then_some(events)
+}
+
+fn sendgrid(events: &[Value]) -> ProviderEvent {
+    let mut kinds: Vec<&str> = Vec::new();
+    for kind in events.iter().filter_map(|e| e.get("event")?.as_str()) {
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    let (event, delivery) = match events {
+        [single] => (
+            kinds.first().map(|k| k.to_string()),
+            string_at(Some(single), &["sg_event_id"]),
+        ),
+        _ => (
+            Some(format!("{} ({} events)", kinds.join(", "), events.len())),
+            None,
+        ),
+    };
+    ProviderEvent::new("sendgrid", event, delivery)
+}
+
+fn cloudevent(cloudevents: &CloudEvents) -> ProviderEvent {
+    let (event, delivery) = match cloudevents.events.as_slice() {
+        // A batch of one reads like a single event
+        [event] => (
+            event.get("type").map(str::to_string),
+            event.get("id").map(str::to_string),
+        ),
+        [] => (None, None),
+        events => {
+            let mut types: Vec<&str> = Vec::new();
+            for kind in events.iter().filter_map(|e| e.get("type")) {
+                if !types.contains(&kind) {
+                    types.push(kind);
+                }
+            }
+            let event = match types.is_empty() {
+                true => format!("{} events", events.len()),
+                false => format!("{} ({} events)", types.join(", "), events.len()),
+            };
+            (Some(event), None)
+        }
+    };
+    ProviderEvent::new("cloudevents", event, delivery)
+}
+
+/// Messaging and voice status callbacks are forms named after the resource
+/// they describe.
+fn twilio(request: &CapturedRequest) -> ProviderEvent {
+    let form = request.form_params();
+    let param = |name: &str| form_param(&form, name);
+
+    let (event, delivery) = if let Some(sid) = param("MessageSid") {
+        let status = param("MessageStatus").or_else(|| param("SmsStatus"));
+        (status.map(|s| format!("message.{}", s)), Some(sid))
+    } else if let Some(sid) = param("CallSid") {
+        (
+            param("CallStatus").map(|s| format!("call.{}", s)),
+            Some(sid),
+        )
+    } else {
+        (None, None)
+    };
+    let delivery = request
+        .header("i-twilio-idempotency-token")
+        .map(str::to_string)
+        .or(delivery);
+    ProviderEvent::new("twilio", event, delivery)
+}
+
+fn form_param(form: &[(String, String)], name: &str) -> Option<String> {
+    form.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+}
+
+/// The string at `path` in `json`, if there is one.
+fn string_at(json: Option<&Value>, path: &[&str]) -> Option<String> {
+    let mut value = json?;
+    for key in path {
+        value = value.get(key)?;
+    }
+    value.as_str().map(str::to_string)
+}
@@ -1,7 +1,341 @@
+use axum::http::StatusCode;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::net::SocketAddr;
+
+use crate::cloudevents::{self, CloudEvents};
+use crate::encoding;
+use crate::provider::{self, ProviderEvent};
+
+/// Request body as received on the wire, tagged with its detected encoding.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "encoding", content = "data", rename_all = "lowercase")]
+pub enum RequestBody {
+    #[default]
+    Empty,
+    Utf8(String),
+    Binary(#[serde(with = "base64_bytes")] Vec<u8>),
+}
+
+impl RequestBody {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.is_empty() {
+            return RequestBody::Empty;
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RequestBody::Utf8(text.to_string()),
+            Err(_) => RequestBody::Binary(bytes.to_vec()),
+        }
+    }
+
+    /// Text for display: pretty-printed JSON, plain text, or a hex dump for binary data.
+    pub fn formatted(&self) -> Option<String> {
+        match self {
+            RequestBody::Empty => None,
+            RequestBody::Utf8(b) => {
+                // Try to pretty-print JSON
+                if let Ok(json) = serde_json::from_str::<serde_json::Value>(b) {
+                    Some(serde_json::to_string_pretty(&json).unwrap_or_else(|_| b.clone()))
+                } else {
+                    Some(b.clone())
+                }
+            }
+            RequestBody::Binary(_) => Some(self.hex_dump()),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            RequestBody::Empty => &[],
+            RequestBody::Utf8(text) => text.as_bytes(),
+            RequestBody::Binary(bytes) => bytes,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.as_bytes().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(self, RequestBody::Empty)
+    }
+
+    pub fn encoding_label(&self) -> &'static str {
+        match self {
+            RequestBody::Empty => "empty",
+            RequestBody::Utf8(_) => "utf-8",
+            RequestBody::Binary(_) => "binary",
+        }
+    }
+
+    /// Guess the format of a binary body from its magic bytes.
+    pub fn sniff_format(&self) -> Option<&'static str> {
+        let bytes = match self {
+            RequestBody::Binary(bytes) => bytes.as_slice(),
+            _ => return None,
+        };
+
+        const SIGNATURES: &[(&[u8], &str)] = &[
+            (&[0x1f, 0x8b], "gzip"),
+            (&[0x28, 0xb5, 0x2f, 0xfd], "zstd"),
+            (b"BZh", "bzip2"),
+            (b"PK\x03\x04", "zip"),
+            (b"\x89PNG\r\n\x1a\n", "png"),
+            (&[0xff, 0xd8, 0xff], "jpeg"),
+            (b"GIF8", "gif"),
+            (b"%PDF", "pdf"),
+        ];
+
+        SIGNATURES
+            .iter()
+            .find(|(magic, _)| bytes.starts_with(magic))
+            .map(|(_, name)| *name)
+    }
+
+    /// Render the body as a classic hex/ASCII dump, 16 bytes per line.
+    pub fn hex_dump(&self) -> String {
+        self.as_bytes()
+            .chunks(16)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+                let (left, right) = hex.split_at(hex.len().min(8));
+                let ascii: String = chunk
+                    .iter()
+                    .map(|&b| {
+                        if b.is_ascii_graphic() || b == b' ' {
+                            b as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                format!(
+                    "{:08x}  {:<23}  {:<23}  |{}|",
+                    i * 16,
+                    left.join(" "),
+                    right.join(" "),
+                    ascii
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+mod base64_bytes {
+    use super::BASE64;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// HTTP headers with every value of repeated ones included. Values are kept
+/// as raw bytes, since peers may send any octets.
+///
+/// HTTP/1 headers are kept in the order received, with names in the case
+/// the client sent. HTTP/2 ones come from hyper's `HeaderMap`, which lists
+/// names in order of first appearance, each followed by all of its values.
+///
+/// Stored as a list of `[name, value]` pairs; a value that is not valid
+/// UTF-8 becomes `{"base64": "..."}`. The name-to-value object written by
+/// older versions is still read.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "HeadersRepr", into = "HeadersRepr")]
+pub struct Headers(Vec<(String, Vec<u8>)>);
+
+impl Headers {
+    /// Headers in their captured order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    /// Headers sorted by name; repeats of a name keep their relative order.
+    pub fn sorted(&self) -> Vec<(&str, &[u8])> {
+        let mut headers: Vec<_> = self.iter().collect();
+        headers.sort_by_key(|(k, _)| k.to_ascii_lowercase());
+        headers
+    }
+
+    /// First value of `name`, case-insensitively, if it is valid UTF-8.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let (_, value) = self.iter().find(|(k, _)| k.eq_ignore_ascii_case(name))?;
+        std::str::from_utf8(value).ok()
+    }
+
+    /// All values of `name` joined with `, `, the way HTTP combines
+    /// repeated fields.
+    pub fn combined(&self, name: &str) -> Option<String> {
+        let values: Vec<_> = self
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| Self::text(v))
+            .collect();
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    /// Header names without repeats, in order of first appearance.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for (name, _) in self.iter() {
+            if !names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// A value as text. Bytes that are not valid UTF-8 are shown as `\xNN`
+    /// escapes rather than lost.
+    pub fn text(value: &[u8]) -> Cow<'_, str> {
+        match std::str::from_utf8(value) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(_) => Cow::Owned(value.escape_ascii().to_string()),
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<Vec<u8>>> FromIterator<(K, V)> for Headers {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum HeadersRepr {
+    List(Vec<(String, HeaderValueRepr)>),
+    Map(BTreeMap<String, String>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum HeaderValueRepr {
+    Text(String),
+    Raw {
+        #[serde(with = "base64_bytes")]
+        base64: Vec<u8>,
+    },
+}
+
+impl From<HeadersRepr> for Headers {
+    fn from(repr: HeadersRepr) -> Self {
+        match repr {
+            HeadersRepr::List(list) => list
+                .into_iter()
+                .map(|(name, value)| match value {
+                    HeaderValueRepr::Text(text) => (name, text.into_bytes()),
+                    HeaderValueRepr::Raw { base64 } => (name, base64),
+                })
+                .collect(),
+            HeadersRepr::Map(map) => map.into_iter().collect(),
+        }
+    }
+}
+
+impl From<Headers> for HeadersRepr {
+    fn from(headers: Headers) -> Self {
+        HeadersRepr::List(
+            headers
+                .0
+                .into_iter()
+                .map(|(name, value)| match String::from_utf8(value) {
+                    Ok(text) => (name, HeaderValueRepr::Text(text)),
+                    Err(e) => (
+                        name,
+                        HeaderValueRepr::Raw {
+                            base64: e.into_bytes(),
+                        },
+                    ),
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Body after undoing the request's `Content-Encoding`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedBody {
+    pub content_encoding: String,
+    pub body: RequestBody,
+    pub error: Option<String>,
+}
+
+/// The response that was sent back for a request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedResponse {
+    pub status: u16,
+    pub headers: Headers,
+    pub body: RequestBody,
+    /// Time from receiving the request to sending the response.
+    pub duration_ms: f64,
+}
+
+/// The connection a request arrived on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    /// The TCP peer; a proxy's address when the client is behind one.
+    pub remote_addr: SocketAddr,
+    /// The listener address the connection was accepted on.
+    pub local_addr: SocketAddr,
+    /// e.g. `HTTP/1.1` or `HTTP/2.0`.
+    pub http_version: String,
+}
+
+/// Outcome of checking a request's webhook signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    /// Provider whose scheme was checked, e.g. `Stripe`.
+    pub scheme: String,
+    pub valid: bool,
+    /// What matched, or why the check failed.
+    pub reason: String,
+}
+
+/// The outcome of sending a captured request on to another server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamResult {
+    /// Full URL the request was sent to.
+    pub target: String,
+    pub timestamp: DateTime<Utc>,
+    pub response: Option<CapturedResponse>,
+    pub error: Option<String>,
+}
+
+impl UpstreamResult {
+    /// One-line outcome, e.g. `200 OK in 12.3 ms`.
+    pub fn summary(&self) -> String {
+        match (&self.response, &self.error) {
+            (Some(response), _) => format!(
+                "{} {} in {:.1} ms",
+                response.status,
+                StatusCode::from_u16(response.status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or(""),
+                response.duration_ms
+            ),
+            (None, Some(error)) => format!("error: {}", error),
+            (None, None) => "no response".to_string(),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CapturedRequest {
@@ -10,8 +344,50 @@ pub struct CapturedRequest {
     pub method: String,
     pub path: String,
     pub query: Option<String>,
-    pub headers: HashMap<String, String>,
-    pub body: Option<String>,
+    pub headers: Headers,
+    pub body: RequestBody,
+    #[serde(default)]
+    pub decoded: Option<DecodedBody>,
+    /// Absent for requests saved before connections were recorded.
+    #[serde(default)]
+    pub connection: Option<ConnectionInfo>,
+    /// The originating client according to `Forwarded` or
+    /// `X-Forwarded-For`. Clients can set these freely, so it is only a claim.
+    #[serde(default)]
+    pub apparent_client: Option<String>,
+    /// The route that handled the request; `None` for the default response.
+    #[serde(default)]
+    pub route: Option<String>,
+    /// Set when the request carries CloudEvents, valid or not.
+    #[serde(default)]
+    pub cloudevents: Option<CloudEvents>,
+    /// The provider and event, when the request looks like a well-known
+    /// provider's webhook.
+    #[serde(default)]
+    pub provider: Option<ProviderEvent>,
+    /// Set when the route verifies signatures.
+    #[serde(default)]
+    pub signature: Option<SignatureCheck>,
+    /// Filled in once the response has been sent; absent when the
+    /// connection was dropped instead.
+    #[serde(default)]
+    pub response: Option<CapturedResponse>,
+    /// Why no response was sent, when the connection was closed, held open
+    /// or broken on purpose.
+    #[serde(default)]
+    pub dropped: Option<String>,
+    /// Results of passing the request through to upstreams as it arrived.
+    #[serde(default)]
+    pub forwards: Vec<UpstreamResult>,
+    /// Results of replaying the request, oldest first.
+    #[serde(default)]
+    pub replays: Vec<UpstreamResult>,
+    /// Mirror results still to come as [`AppEvent::Mirrored`]. Only
+    /// meaningful while the server runs, so it is not saved.
+    ///
+    /// [`AppEvent::Mirrored`]: crate::event::AppEvent::Mirrored
+    #[serde(skip)]
+    pub pending_mirrors: usize,
 }
 
 impl CapturedRequest {
@@ -20,10 +396,10 @@ impl CapturedRequest {
         method: String,
         path: String,
         query: Option<String>,
-        headers: HashMap<String, String>,
-        body: Option<String>,
+        headers: Headers,
+        body: RequestBody,
     ) -> Self {
-        Self {
+        let mut request = Self {
             id,
             timestamp: Utc::now(),
             method,
@@ -31,7 +407,115 @@ impl CapturedRequest {
             query,
             headers,
             body,
+            decoded: None,
+            connection: None,
+            apparent_client: None,
+            route: None,
+            cloudevents: None,
+            provider: None,
+            signature: None,
+            response: None,
+            dropped: None,
+            forwards: Vec::new(),
+            replays: Vec::new(),
+            pending_mirrors: 0,
+        };
+        request.decoded = request.decode_body();
+        request.apparent_client = request.forwarded_for();
+        request.cloudevents = cloudevents::parse(&request);
+        request.provider = provider::detect(&request);
+        request
+    }
+
+    /// Keep the upstream results of `previous`, an earlier copy of this
+    /// request, that this copy lacks. Mirror and replay results arrive on
+    /// their own, so a copy made when the response was sent can miss them.
+    pub fn merge_results(&mut self, previous: &CapturedRequest) {
+        // The relayed forward stays first; mirrors follow it
+        add_missing(&mut self.forwards, &previous.forwards);
+        add_missing(&mut self.replays, &previous.replays);
+        self.replays.sort_by_key(|r| r.timestamp);
+    }
+
+    /// The first client named by `Forwarded`, or else `X-Forwarded-For`.
+    /// Proxies append to these, so the first entry is the furthest hop.
+    fn forwarded_for(&self) -> Option<String> {
+        let from_forwarded = self.header("forwarded").and_then(|value| {
+            value
+                .split(',')
+                .next()?
+                .split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("for"))
+                .map(|(_, node)| node.trim().trim_matches('"').to_string())
+        });
+
+        from_forwarded
+            .or_else(|| {
+                let value = self.header("x-forwarded-for")?;
+                Some(value.split(',').next()?.trim().to_string())
+            })
+            .filter(|client| !client.is_empty())
+    }
+
+    fn decode_body(&self) -> Option<DecodedBody> {
+        let content_encoding = self.header("content-encoding")?.trim();
+        if content_encoding.is_empty()
+            || content_encoding.eq_ignore_ascii_case("identity")
+            || self.body.is_empty()
+        {
+            return None;
         }
+
+        let (body, error) = match encoding::decode(content_encoding, self.body.as_bytes()) {
+            Ok(bytes) => (RequestBody::from_bytes(&bytes), None),
+            Err(e) => (RequestBody::Empty, Some(e)),
+        };
+
+        Some(DecodedBody {
+            content_encoding: content_encoding.to_string(),
+            body,
+            error,
+        })
+    }
+
+    /// Case-insensitive header lookup.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// The body to show: the decoded form when decoding succeeded, else the wire bytes.
+    pub fn display_body(&self) -> &RequestBody {
+        match &self.decoded {
+            Some(DecodedBody {
+                body, error: None, ..
+            }) => body,
+            _ => &self.body,
+        }
+    }
+
+    /// Decoded query string parameters, in order of appearance.
+    pub fn query_params(&self) -> Vec<(String, String)> {
+        self.query
+            .as_deref()
+            .and_then(|q| serde_urlencoded::from_str(q).ok())
+            .unwrap_or_default()
+    }
+
+    /// Fields of an `application/x-www-form-urlencoded` body, in order.
+    pub fn form_params(&self) -> Vec<(String, String)> {
+        let is_form = self
+            .header("content-type")
+            .is_some_and(|ct| ct.starts_with("application/x-www-form-urlencoded"));
+        if !is_form {
+            return Vec::new();
+        }
+        serde_urlencoded::from_bytes(self.display_body().as_bytes()).unwrap_or_default()
+    }
+
+    /// The displayed body parsed as JSON, if it is valid JSON.
+    pub fn json_body(&self) -> Option<serde_json::Value> {
+        serde_json::from_slice(self.display_body().as_bytes()).ok()
     }
 
     pub fn full_path(&self) -> String {
@@ -44,17 +528,6 @@ impl CapturedRequest {
     pub fn timestamp_display(&self) -> String {
         self.timestamp.format("%H:%M:%S").to_string()
     }
-
-    pub fn formatted_body(&self) -> Option<String> {
-        self.body.as_ref().map(|b| {
-            // Try to pretty-print JSON
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(b) {
-                serde_json::to_string_pretty(&json).unwrap_or_else(|_| b.clone())
-            } else {
-                b.clone()
-            }
-        })
-    }
 }
 
 impl fmt::Display for CapturedRequest {
@@ -68,3 +541,15 @@ impl fmt::Display for CapturedRequest {
         )
     }
 }
+
+/// Append the results of `earlier` that `results` does not have yet.
+fn add_missing(results: &mut Vec<UpstreamResult>, earlier: &[UpstreamResult]) {
+    for result in earlier {
+        let known = results
+            .iter()
+            .any(|r| r.target == result.target && r.timestamp == result.timestamp);
+        if !known {
+            results.push(result.clone());
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+
+use crate::request::CapturedRequest;
+use crate::signature::SignatureScheme;
+
+/// Built-in handlers for the verification handshakes providers perform
+/// before they start delivering events. When the handshake is not
+/// recognized, the route's configured response is used instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Responder {
+    /// Try every handshake below.
+    Auto,
+    /// Slack Events API `url_verification`: echo `challenge`.
+    Slack,
+    /// Discord interactions PING (`type: 1`): answer with PONG. Discord
+    /// also sends badly signed PINGs and expects a 401, so only PINGs that
+    /// pass the route's `discord` signature check are answered.
+    Discord,
+    /// Microsoft Graph subscriptions: echo the `validationToken` query parameter.
+    Msgraph,
+    /// Twitch EventSub `webhook_callback_verification`: echo `challenge`.
+    Twitch,
+    /// Meta (Facebook, WhatsApp, Instagram) subscriptions: echo `hub.challenge`.
+    Meta,
+}
+
+/// The canned answer to a recognized handshake.
+#[derive(Debug, Clone)]
+pub struct ChallengeResponse {
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Responder {
+    const ALL: [Responder; 5] = [
+        Responder::Slack,
+        Responder::Discord,
+        Responder::Msgraph,
+        Responder::Twitch,
+        Responder::Meta,
+    ];
+
+    pub fn respond(&self, request: &CapturedRequest) -> Option<ChallengeResponse> {
+        match self {
+            Responder::Auto => Self::ALL.iter().find_map(|r| r.respond(request)),
+            Responder::Slack => {
+                let json = request.json_body()?;
+                if json.get("type")?.as_str()? != "url_verification" {
+                    return None;
+                }
+                let challenge = json.get("challenge")?.as_str()?;
+                Some(ChallengeResponse {
+                    content_type: "application/json",
+                    body: serde_json::json!({ "challenge": challenge }).to_string(),
+                })
+            }
+            Responder::Discord => {
+                let signature = request.signature.as_ref()?;
+                if signature.scheme != SignatureScheme::Discord.label() || !signature.valid {
+                    return None;
+                }
+                let json = request.json_body()?;
+                if json.get("type")?.as_u64()? != 1 {
+                    return None;
+                }
+                Some(ChallengeResponse {
+                    content_type: "application/json",
+                    body: r#"{"type":1}"#.to_string(),
+                })
+            }
+            Responder::Msgraph => query_param(request, "validationToken").map(plain_text),
+            Responder::Twitch => {
+                if request.header("twitch-eventsub-message-type")?
+                    != "webhook_callback_verification"
+                {
+                    return None;
+                }
+                let json = request.json_body()?;
+                json.get("challenge")?
+                    .as_str()
+                    .map(|c| plain_text(c.to_string()))
+            }
+            Responder::Meta => {
+                if query_param(request, "hub.mode")? != "subscribe" {
+                    return None;
+                }
+                query_param(request, "hub.challenge").map(plain_text)
+            }
+        }
+    }
+}
+
+fn query_param(request: &CapturedRequest, name: &str) -> Option<String> {
+    request
+        .query_params()
+        .into_iter()
+        .find(|(k, _)| k == name)
+        .map(|(_, v)| v)
+}
+
+fn plain_text(body: String) -> ChallengeResponse {
+    ChallengeResponse {
+        content_type: "text/plain",
+        body,
+    }
+}
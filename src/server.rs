@@ -1,66 +1,131 @@
 use axum::{
     body::Body,
-    extract::State,
+    extract::{Extension, State},
     http::{HeaderMap, Method, Request, StatusCode},
     response::{IntoResponse, Response},
     routing::any,
     Router,
 };
 use bytes::Bytes;
+use chrono::Utc;
 use http_body_util::BodyExt;
 use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::net::TcpListener;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
-use crate::config::Config;
-use crate::request::CapturedRequest;
+use crate::chaos;
+use crate::config::{
+    self, Config, ConfigOverrides, ResponseConfig, ResponseMode, RouteMatch, SharedConfig,
+    UpstreamConfig,
+};
+use crate::connection::{self, Connection, ReceivedHeaders};
+use crate::event::AppEvent;
+use crate::request::{
+    CapturedRequest, CapturedResponse, ConnectionInfo, Headers, RequestBody, UpstreamResult,
+};
+use crate::template;
+use crate::upstream;
+use crate::watcher;
 
 static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(1);
 
+/// Continue numbering after requests restored from a saved session, so ids
+/// stay unique within it.
+pub fn resume_request_ids(last_id: u64) {
+    REQUEST_COUNTER.fetch_max(last_id + 1, Ordering::SeqCst);
+}
+
 #[derive(Clone)]
 pub struct AppState {
-    pub config: Arc<Config>,
-    pub tx: mpsc::UnboundedSender<CapturedRequest>,
+    pub config: SharedConfig,
+    pub tx: mpsc::UnboundedSender<AppEvent>,
+    /// Number of requests each sequenced route has answered, by route index.
+    pub sequence_positions: Arc<Mutex<HashMap<usize, usize>>>,
 }
 
+impl AppState {
+    pub fn config(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Install a new config. Sequence positions refer to route indexes, so
+    /// they start over.
+    pub fn replace_config(&self, config: Config) {
+        *self.config.write().unwrap() = Arc::new(config);
+        self.sequence_positions.lock().unwrap().clear();
+    }
+
+    /// The response a matched route should give, advancing its sequence if
+    /// it has one.
+    fn route_response<'a>(&self, route: &RouteMatch<'a>) -> &'a ResponseConfig {
+        let Some(sequence) = &route.route.sequence else {
+            return &route.route.response;
+        };
+
+        let mut positions = self.sequence_positions.lock().unwrap();
+        let position = positions.entry(route.index).or_insert(0);
+        let step = *position;
+        *position += 1;
+
+        sequence.response_at(step).unwrap_or(&route.route.response)
+    }
+}
+
+/// Serve requests on `listener`, which is bound by the caller so it can
+/// report a taken port before announcing the address.
 pub async fn run_server(
-    config: Config,
-    tx: mpsc::UnboundedSender<CapturedRequest>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    listener: TcpListener,
+    config: SharedConfig,
+    config_path: Option<PathBuf>,
+    overrides: ConfigOverrides,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) -> io::Result<()> {
     let state = AppState {
-        config: Arc::new(config.clone()),
-        tx,
+        config,
+        tx: tx.clone(),
+        sequence_positions: Arc::new(Mutex::new(HashMap::new())),
     };
 
+    if let Some(path) = config_path {
+        tokio::spawn(watcher::watch_config(path, overrides, state.clone(), tx));
+    }
+
     let app = Router::new()
-        .route("/{*path}", any(catch_all_handler))
+        .route("/*path", any(catch_all_handler))
         .route("/", any(catch_all_handler))
-        .with_state(state);
-
-    let addr = config.address();
-    let listener = TcpListener::bind(&addr).await?;
+        .with_state(state.clone());
 
-    axum::serve(listener, app).await?;
-
-    Ok(())
+    connection::serve(listener, app).await
 }
 
 async fn catch_all_handler(
     State(state): State<AppState>,
+    Extension(connection): Extension<Connection>,
+    received: Option<Extension<ReceivedHeaders>>,
     method: Method,
     headers: HeaderMap,
     request: Request<Body>,
 ) -> impl IntoResponse {
+    let started = Instant::now();
+    let version = request.version();
     let path = request.uri().path().to_string();
     let query = request.uri().query().map(|q| q.to_string());
 
-    // Extract headers into HashMap
-    let headers_map: HashMap<String, String> = headers
-        .iter()
-        .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
-        .collect();
+    // Without the head as received (HTTP/2), fall back to HeaderMap, which
+    // yields names in order of first appearance, each followed by all of
+    // its values
+    let headers_map: Headers = match received {
+        Some(Extension(ReceivedHeaders(headers))) => headers,
+        None => headers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_bytes()))
+            .collect(),
+    };
 
     // Extract body
     let body_bytes: Bytes = request
@@ -70,44 +135,278 @@ async fn catch_all_handler(
         .map(|b| b.to_bytes())
         .unwrap_or_default();
 
-    let body = if body_bytes.is_empty() {
-        None
-    } else {
-        String::from_utf8(body_bytes.to_vec()).ok()
-    };
+    let body = RequestBody::from_bytes(&body_bytes);
 
     // Create captured request
     let id = REQUEST_COUNTER.fetch_add(1, Ordering::SeqCst);
-    let captured = CapturedRequest::new(
-        id,
-        method.to_string(),
-        path.clone(),
-        query,
-        headers_map,
-        body,
-    );
+    let mut captured = CapturedRequest::new(id, method.to_string(), path, query, headers_map, body);
+    captured.connection = Some(ConnectionInfo {
+        remote_addr: connection.remote_addr(),
+        local_addr: connection.local_addr(),
+        http_version: format!("{:?}", version),
+    });
+
+    // Get configured response
+    let config = state.config();
+    let route = config.find_route(&captured);
+    captured.route = route.as_ref().map(|m| m.route.describe(m.index));
+
+    let signature = route.as_ref().and_then(|m| m.route.signature.as_ref());
+    if let Some(signature) = signature {
+        let check = signature.verify(&captured, Utc::now());
+        if signature.reject && !check.valid {
+            let body = serde_json::json!({
+                "error": "invalid signature",
+                "reason": check.reason,
+            });
+            captured.signature = Some(check);
+            return reject(&state, captured, StatusCode::UNAUTHORIZED, body, started).await;
+        }
+        captured.signature = Some(check);
+    }
+
+    let cloudevents = route.as_ref().and_then(|m| m.route.cloudevents.as_ref());
+    if let Some(cloudevents) = cloudevents.filter(|c| c.reject) {
+        let problems = match &captured.cloudevents {
+            Some(events) => events.problems(),
+            None if cloudevents.require => vec!["not a CloudEvent".to_string()],
+            None => Vec::new(),
+        };
+        if !problems.is_empty() {
+            let body = serde_json::json!({
+                "error": "invalid CloudEvent",
+                "problems": problems,
+            });
+            return reject(&state, captured, StatusCode::BAD_REQUEST, body, started).await;
+        }
+    }
+
+    let challenge = route
+        .as_ref()
+        .and_then(|m| m.route.responder)
+        .and_then(|r| r.respond(&captured));
 
     // Send to TUI (ignore error if receiver is dropped)
-    let _ = state.tx.send(captured);
+    let _ = state.tx.send(AppEvent::Request(Box::new(captured.clone())));
 
-    // Get configured response
-    let response_config = state.config.get_response(method.as_str(), &path);
+    // Answer provider verification handshakes directly, without using up a
+    // step of the route's sequence
+    if let Some(challenge) = challenge {
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .header("Content-Type", challenge.content_type)
+            .body(Body::from(challenge.body))
+            .unwrap();
+        return record_response(&state, captured, response, started).await;
+    }
+
+    let (response_config, params) = match &route {
+        Some(m) => (state.route_response(m), &m.params),
+        None => (&config.response, &HashMap::new()),
+    };
+    let forward = match &route {
+        Some(m) => m.route.forward.as_ref(),
+        None => config.forward.as_ref(),
+    };
+    let context = template::context(&captured, params);
+
+    // Build response, or fetch it from the upstream
+    let mut mirrors_guard = None;
+    let response = match forward {
+        Some(forward) => {
+            mirrors_guard = Some(mirror(&state, &captured, &forward.mirror));
+            captured.pending_mirrors = forward.mirror.len();
+            let result = upstream::send(&captured, &forward.upstream).await;
+            let response = relay_response(&result);
+            captured.forwards.push(result);
+            response
+        }
+        None => build_response(response_config, &context).unwrap_or_else(|e| {
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from(e))
+                .unwrap()
+        }),
+    };
+
+    if let Some(delay) = response_config.delay.as_ref().or(config.delay.as_ref()) {
+        tokio::time::sleep(delay.sample()).await;
+    }
+
+    let chaos = if response_config.chaos.is_empty() {
+        &config.chaos
+    } else {
+        &response_config.chaos
+    };
+    let response = match config::roll_chaos(chaos) {
+        Some(fault) => match chaos::inject(fault, response, &connection, version).await {
+            Ok(response) => response,
+            Err(reason) => {
+                record_dropped(&state, captured, reason);
+                return abandon(&connection).await;
+            }
+        },
+        None => response,
+    };
+
+    match response_config.mode {
+        ResponseMode::Respond => record_response(&state, captured, response, started).await,
+        ResponseMode::Hang => {
+            record_dropped(&state, captured, "held the connection open (mode: hang)");
+            // Mirror results must not wait for the client to give up
+            drop(mirrors_guard);
+            connection.closed().await;
+            abandon(&connection).await
+        }
+        ResponseMode::Close => {
+            record_dropped(&state, captured, "closed the connection (mode: close)");
+            abandon(&connection).await
+        }
+    }
+}
+
+/// Turn the request away with a JSON error instead of the route's response.
+async fn reject(
+    state: &AppState,
+    captured: CapturedRequest,
+    status: StatusCode,
+    body: serde_json::Value,
+    started: Instant,
+) -> Response {
+    let _ = state.tx.send(AppEvent::Request(Box::new(captured.clone())));
+    let response = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    record_response(state, captured, response, started).await
+}
+
+/// Attach the response about to be sent to the captured request and pass
+/// the update on. Bodies are built in memory, so collecting one is cheap.
+async fn record_response(
+    state: &AppState,
+    mut captured: CapturedRequest,
+    response: Response,
+    started: Instant,
+) -> Response {
+    let (parts, body) = response.into_parts();
+    let bytes = body
+        .collect()
+        .await
+        .map(|b| b.to_bytes())
+        .unwrap_or_default();
+
+    captured.response = Some(CapturedResponse {
+        status: parts.status.as_u16(),
+        headers: parts
+            .headers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_bytes()))
+            .collect(),
+        body: RequestBody::from_bytes(&bytes),
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    });
+    let _ = state.tx.send(AppEvent::Responded(Box::new(captured)));
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+/// Note why the request gets no response and pass the update on.
+fn record_dropped(state: &AppState, mut captured: CapturedRequest, reason: &str) {
+    captured.dropped = Some(reason.to_string());
+    let _ = state.tx.send(AppEvent::Responded(Box::new(captured)));
+}
+
+/// Drop the connection without responding. The connection task tears down
+/// this handler along with it, so this never returns.
+async fn abandon(connection: &Connection) -> Response {
+    connection.close();
+    std::future::pending().await
+}
+
+/// Send copies of the request to `mirrors` in the background. Their results
+/// are reported once the returned guard is dropped, so they always arrive
+/// after the request's own [`AppEvent::Responded`] update rather than being
+/// overwritten by it.
+fn mirror(
+    state: &AppState,
+    request: &CapturedRequest,
+    mirrors: &[UpstreamConfig],
+) -> oneshot::Sender<()> {
+    let (guard, released) = oneshot::channel();
 
-    // Build response
+    let sends: Vec<_> = mirrors
+        .iter()
+        .map(|upstream| {
+            let request = request.clone();
+            let upstream = upstream.clone();
+            tokio::spawn(async move { upstream::send(&request, &upstream).await })
+        })
+        .collect();
+
+    let tx = state.tx.clone();
+    let id = request.id;
+    tokio::spawn(async move {
+        let _ = released.await;
+        for send in sends {
+            if let Ok(result) = send.await {
+                let _ = tx.send(AppEvent::Mirrored(id, Box::new(result)));
+            }
+        }
+    });
+
+    guard
+}
+
+/// The upstream's response as our own, or a 502 when there was none.
+fn relay_response(result: &UpstreamResult) -> Response {
+    let Some(upstream) = &result.response else {
+        return Response::builder()
+            .status(StatusCode::BAD_GATEWAY)
+            .body(Body::from(format!(
+                "{}: {}\n",
+                result.target,
+                result.summary()
+            )))
+            .unwrap();
+    };
+
+    let mut response = Response::builder().status(upstream.status);
+    // Values are relayed byte for byte, whatever their encoding
+    for (name, value) in upstream.headers.iter() {
+        if !upstream::is_hop_header(name) {
+            response = response.header(name, value);
+        }
+    }
+    response
+        .body(Body::from(upstream.body.as_bytes().to_vec()))
+        .unwrap_or_else(|e| {
+            Response::builder()
+                .status(StatusCode::BAD_GATEWAY)
+                .body(Body::from(format!("invalid upstream response: {}", e)))
+                .unwrap()
+        })
+}
+
+fn build_response(
+    response_config: &ResponseConfig,
+    context: &serde_json::Value,
+) -> Result<Response, String> {
     let status = StatusCode::from_u16(response_config.status).unwrap_or(StatusCode::OK);
 
     let mut response = Response::builder().status(status);
 
     for (key, value) in &response_config.headers {
+        let value = template::render(value, context)
+            .map_err(|e| format!("template error in header {}: {}", key, e))?;
         response = response.header(key, value);
     }
 
+    let body = template::render(&response_config.body, context)
+        .map_err(|e| format!("template error in body: {}", e))?;
+
     response
-        .body(Body::from(response_config.body.clone()))
-        .unwrap_or_else(|_| {
-            Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("Internal Server Error"))
-                .unwrap()
-        })
+        .body(Body::from(body))
+        .map_err(|e| format!("invalid response: {}", e))
 }
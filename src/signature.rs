@@ -0,0 +1,499 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, VerifyingKey};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::request::{CapturedRequest, SignatureCheck};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Check each request's webhook signature against a shared secret, or for
+/// Discord, the application's public key.
+///
+/// ```yaml
+/// signature:
+///   scheme: stripe
+///   secret: "whsec_..."
+///   tolerance: 300      # seconds; 0 accepts any timestamp
+///   reject: true        # answer 401 when the check fails
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignatureConfig {
+    pub scheme: SignatureScheme,
+    /// The signing secret; for Discord, the hex public key.
+    #[serde(alias = "public_key")]
+    pub secret: String,
+    /// How far the signed timestamp may be from now, for schemes that sign
+    /// one (Stripe, Slack, Standard Webhooks, Discord).
+    #[serde(default = "default_tolerance")]
+    pub tolerance: u64,
+    /// Answer requests that fail the check with 401 instead of the route's
+    /// response.
+    #[serde(default)]
+    pub reject: bool,
+}
+
+fn default_tolerance() -> u64 {
+    300
+}
+
+/// How a provider signs its requests. All but Discord use HMAC-SHA256.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureScheme {
+    /// `X-Hub-Signature-256: sha256=<hex>` over the body.
+    Github,
+    /// `Stripe-Signature: t=<ts>,v1=<hex>` over `<ts>.<body>`.
+    Stripe,
+    /// `X-Shopify-Hmac-Sha256: <base64>` over the body.
+    Shopify,
+    /// `X-Slack-Signature: v0=<hex>` over `v0:<ts>:<body>`, with the
+    /// timestamp in `X-Slack-Request-Timestamp`.
+    Slack,
+    /// `webhook-signature: v1,<base64>` over `<id>.<ts>.<body>`, with a
+    /// base64 `whsec_` secret. Svix sends the same headers prefixed `svix-`.
+    #[serde(alias = "svix")]
+    StandardWebhooks,
+    /// `X-Signature-Ed25519: <hex>`, an Ed25519 signature over
+    /// `<ts><body>` with the timestamp in `X-Signature-Timestamp`.
+    Discord,
+}
+
+impl SignatureScheme {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SignatureScheme::Github => "GitHub",
+            SignatureScheme::Stripe => "Stripe",
+            SignatureScheme::Shopify => "Shopify",
+            SignatureScheme::Slack => "Slack",
+            SignatureScheme::StandardWebhooks => "Standard Webhooks",
+            SignatureScheme::Discord => "Discord",
+        }
+    }
+}
+
+impl SignatureConfig {
+    /// The HMAC key, or Discord's public key. Standard Webhooks secrets are
+    /// base64 after an optional `whsec_` prefix, Discord's key is hex, and
+    /// the other providers use the secret as is.
+    pub fn key(&self) -> Result<Vec<u8>, String> {
+        if self.secret.is_empty() {
+            return Err("must not be empty".to_string());
+        }
+        match self.scheme {
+            SignatureScheme::StandardWebhooks => {
+                let encoded = self.secret.strip_prefix("whsec_").unwrap_or(&self.secret);
+                BASE64
+                    .decode(encoded)
+                    .map_err(|e| format!("not a base64 whsec_ secret: {}", e))
+            }
+            SignatureScheme::Discord => {
+                let key = decode_hex(&self.secret)
+                    .filter(|k| k.len() == 32)
+                    .ok_or("not a 64-digit hex public key")?;
+                verifying_key(&key)?;
+                Ok(key)
+            }
+            _ => Ok(self.secret.as_bytes().to_vec()),
+        }
+    }
+
+    /// Verify `request`'s signature as of `now`.
+    pub fn verify(&self, request: &CapturedRequest, now: DateTime<Utc>) -> SignatureCheck {
+        let result = self
+            .key()
+            .map_err(|e| format!("secret {}", e))
+            .and_then(|key| self.check(&key, request, now));
+
+        SignatureCheck {
+            scheme: self.scheme.label().to_string(),
+            valid: result.is_ok(),
+            reason: result
+                .err()
+                .unwrap_or_else(|| "signature matches".to_string()),
+        }
+    }
+
+    fn check(
+        &self,
+        key: &[u8],
+        request: &CapturedRequest,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        // Signatures cover the body exactly as sent, before any decompression
+        let body = request.body.as_bytes();
+
+        match self.scheme {
+            SignatureScheme::Github => {
+                let header = required(request, "X-Hub-Signature-256")?;
+                let signature = header
+                    .strip_prefix("sha256=")
+                    .and_then(decode_hex)
+                    .ok_or("X-Hub-Signature-256 is not sha256=<hex>")?;
+                verify(key, &[body], &signature)
+            }
+            SignatureScheme::Stripe => {
+                let header = required(request, "Stripe-Signature")?;
+                let mut timestamp = None;
+                let mut signatures = Vec::new();
+                for (name, value) in header.split(',').filter_map(|p| p.trim().split_once('=')) {
+                    match name {
+                        "t" => timestamp = Some(value),
+                        "v1" => signatures.extend(decode_hex(value)),
+                        _ => {}
+                    }
+                }
+                let timestamp = timestamp.ok_or("Stripe-Signature has no t= timestamp")?;
+                if signatures.is_empty() {
+                    return Err("Stripe-Signature has no v1 signature".to_string());
+                }
+                let signed = [timestamp.as_bytes(), b".", body];
+                if !signatures.iter().any(|s| verify(key, &signed, s).is_ok()) {
+                    return Err("no v1 signature matches".to_string());
+                }
+                self.check_timestamp(timestamp, now)
+            }
+            SignatureScheme::Shopify => {
+                let header = required(request, "X-Shopify-Hmac-Sha256")?;
+                let signature = BASE64
+                    .decode(header.trim())
+                    .map_err(|_| "X-Shopify-Hmac-Sha256 is not base64")?;
+                verify(key, &[body], &signature)
+            }
+            SignatureScheme::Slack => {
+                let timestamp = required(request, "X-Slack-Request-Timestamp")?;
+                let header = required(request, "X-Slack-Signature")?;
+                let signature = header
+                    .strip_prefix("v0=")
+                    .and_then(decode_hex)
+                    .ok_or("X-Slack-Signature is not v0=<hex>")?;
+                verify(key, &[b"v0:", timestamp.as_bytes(), b":", body], &signature)?;
+                self.check_timestamp(timestamp, now)
+            }
+            SignatureScheme::StandardWebhooks => {
+                let svix = request.header("webhook-signature").is_none()
+                    && request.header("svix-signature").is_some();
+                let prefix = if svix { "svix" } else { "webhook" };
+                let id = required(request, &format!("{}-id", prefix))?;
+                let timestamp = required(request, &format!("{}-timestamp", prefix))?;
+                let header = required(request, &format!("{}-signature", prefix))?;
+                let signatures: Vec<_> = header
+                    .split_whitespace()
+                    .filter_map(|s| s.strip_prefix("v1,"))
+                    .filter_map(|s| BASE64.decode(s).ok())
+                    .collect();
+                if signatures.is_empty() {
+                    return Err(format!("{}-signature has no v1 signature", prefix));
+                }
+                let signed = [id.as_bytes(), b".", timestamp.as_bytes(), b".", body];
+                if !signatures.iter().any(|s| verify(key, &signed, s).is_ok()) {
+                    return Err("no v1 signature matches".to_string());
+                }
+                self.check_timestamp(timestamp, now)
+            }
+            SignatureScheme::Discord => {
+                let header = required(request, "X-Signature-Ed25519")?;
+                let timestamp = required(request, "X-Signature-Timestamp")?;
+                let signature = decode_hex(header)
+                    .and_then(|s| Signature::from_slice(&s).ok())
+                    .ok_or("X-Signature-Ed25519 is not a 128-digit hex signature")?;
+                let signed = [timestamp.as_bytes(), body].concat();
+                verifying_key(key)?
+                    .verify_strict(&signed, &signature)
+                    .map_err(|_| "signature does not match")?;
+                self.check_timestamp(timestamp, now)
+            }
+        }
+    }
+
+    /// Reject signed timestamps too far from `now`, which guards against
+    /// replayed deliveries.
+    fn check_timestamp(&self, timestamp: &str, now: DateTime<Utc>) -> Result<(), String> {
+        let seconds: i64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| format!("timestamp {:?} is not a Unix time", timestamp))?;
+        let age = now.timestamp() - seconds;
+        if self.tolerance > 0 && age.unsigned_abs() > self.tolerance {
+            let when = if age >= 0 { "old" } else { "in the future" };
+            return Err(format!(
+                "timestamp is {} s {}, outside the {} s tolerance",
+                age.unsigned_abs(),
+                when,
+                self.tolerance
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn required<'a>(request: &'a CapturedRequest, name: &str) -> Result<&'a str, String> {
+    request
+        .header(name)
+        .ok_or_else(|| format!("missing {} header", name))
+}
+
+/// Compare the HMAC of `parts` with `expected` in constant time.
+fn verify(key: &[u8], parts: &[&[u8]], expected: &[u8]) -> Result<(), String> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.verify_slice(expected)
+        .map_err(|_| "signature does not match".to_string())
+}
+
+fn verifying_key(key: &[u8]) -> Result<VerifyingKey, String> {
+    key.try_into()
+        .ok()
+        .and_then(|k| VerifyingKey::from_bytes(k).ok())
+        .ok_or_else(|| "not a valid Ed25519 public key".to_string())
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestBody;
+
+    // Signatures below were computed independently of this module
+    const BODY: &[u8] = br#"{"id":"evt_1","type":"ping"}"#;
+    const TAMPERED: &[u8] = br#"{"id":"evt_2","type":"ping"}"#;
+    const SIGNED_AT: i64 = 1_700_000_000;
+
+    fn config(scheme: SignatureScheme, secret: &str) -> SignatureConfig {
+        SignatureConfig {
+            scheme,
+            secret: secret.to_string(),
+            tolerance: 300,
+            reject: false,
+        }
+    }
+
+    fn request(headers: &[(&str, &str)], body: &[u8]) -> CapturedRequest {
+        CapturedRequest::new(
+            1,
+            "POST".to_string(),
+            "/hook".to_string(),
+            None,
+            headers.iter().copied().collect(),
+            RequestBody::from_bytes(body),
+        )
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    /// Check `headers` over the good body and the tampered one.
+    fn assert_good_and_tampered(config: &SignatureConfig, headers: &[(&str, &str)]) {
+        let good = config.verify(&request(headers, BODY), at(SIGNED_AT));
+        assert!(good.valid, "{}: {}", good.scheme, good.reason);
+        let tampered = config.verify(&request(headers, TAMPERED), at(SIGNED_AT));
+        assert!(
+            !tampered.valid,
+            "{} accepted a tampered body",
+            tampered.scheme
+        );
+    }
+
+    #[test]
+    fn github() {
+        // The example from GitHub's webhook documentation
+        let config = config(SignatureScheme::Github, "It's a Secret to Everybody");
+        let header = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        let headers = [("X-Hub-Signature-256", header)];
+        assert!(
+            config
+                .verify(&request(&headers, b"Hello, World!"), at(0))
+                .valid
+        );
+        assert!(
+            !config
+                .verify(&request(&headers, b"Hello, World?"), at(0))
+                .valid
+        );
+
+        let missing = config.verify(&request(&[], b"Hello, World!"), at(0));
+        assert_eq!(missing.reason, "missing X-Hub-Signature-256 header");
+    }
+
+    #[test]
+    fn stripe() {
+        let header =
+            "t=1700000000,v1=33ff6664879612e191c4e01184f3da32597a3c9b33ca2655fac317d01c5abc3f";
+        assert_good_and_tampered(
+            &config(SignatureScheme::Stripe, "whsec_test"),
+            &[("Stripe-Signature", header)],
+        );
+    }
+
+    #[test]
+    fn stripe_accepts_any_of_several_signatures() {
+        let header = "t=1700000000,v1=00,v0=ab,\
+            v1=33ff6664879612e191c4e01184f3da32597a3c9b33ca2655fac317d01c5abc3f";
+        let config = config(SignatureScheme::Stripe, "whsec_test");
+        let check = config.verify(
+            &request(&[("Stripe-Signature", header)], BODY),
+            at(SIGNED_AT),
+        );
+        assert!(check.valid, "{}", check.reason);
+    }
+
+    #[test]
+    fn shopify() {
+        assert_good_and_tampered(
+            &config(SignatureScheme::Shopify, "shpss_test"),
+            &[(
+                "X-Shopify-Hmac-Sha256",
+                "7R+nvTrfrAbkRZqbSA0Mei4dCudAnsAElrt9rKWIch8=",
+            )],
+        );
+    }
+
+    #[test]
+    fn slack() {
+        assert_good_and_tampered(
+            &config(SignatureScheme::Slack, "slack_test"),
+            &[
+                ("X-Slack-Request-Timestamp", "1700000000"),
+                (
+                    "X-Slack-Signature",
+                    "v0=398c8112a3968786632af37e5bb5dde5a5ee7d70eb3b58693c0edce19bb38d38",
+                ),
+            ],
+        );
+    }
+
+    const STANDARD_SECRET: &str = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+    const STANDARD_SIGNATURE: &str = "v1,BWNfYfylSBDDOVblQaWCsR91HeIouEgHkBUCoeWnCNM=";
+
+    #[test]
+    fn standard_webhooks() {
+        assert_good_and_tampered(
+            &config(SignatureScheme::StandardWebhooks, STANDARD_SECRET),
+            &[
+                ("webhook-id", "msg_1"),
+                ("webhook-timestamp", "1700000000"),
+                ("webhook-signature", STANDARD_SIGNATURE),
+            ],
+        );
+    }
+
+    #[test]
+    fn standard_webhooks_reads_svix_headers() {
+        assert_good_and_tampered(
+            &config(SignatureScheme::StandardWebhooks, STANDARD_SECRET),
+            &[
+                ("svix-id", "msg_1"),
+                ("svix-timestamp", "1700000000"),
+                (
+                    "svix-signature",
+                    &format!("v1,bm9wZQ== {}", STANDARD_SIGNATURE),
+                ),
+            ],
+        );
+    }
+
+    // RFC 8032 test key 1
+    const DISCORD_PUBLIC_KEY: &str =
+        "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+
+    #[test]
+    fn discord() {
+        let signature = "f3a989a9f540dec6d0d305e8b5b974ec1fac9aa370bd1abdab1bad791d362ed6\
+            7bf9bec69c7ec9efd6ad8209f379477bf1a87ac165664ced17a03930737af702";
+        assert_good_and_tampered(
+            &config(SignatureScheme::Discord, DISCORD_PUBLIC_KEY),
+            &[
+                ("X-Signature-Ed25519", signature),
+                ("X-Signature-Timestamp", "1700000000"),
+            ],
+        );
+    }
+
+    #[test]
+    fn discord_key_must_be_hex_public_key() {
+        assert!(config(SignatureScheme::Discord, DISCORD_PUBLIC_KEY)
+            .key()
+            .is_ok());
+        assert!(config(SignatureScheme::Discord, "d75a98").key().is_err());
+        assert!(config(SignatureScheme::Discord, "not hex").key().is_err());
+    }
+
+    /// Verify a correct Stripe and Standard Webhooks signature at `now`.
+    fn verify_timestamps(tolerance: u64, now: i64) -> [SignatureCheck; 2] {
+        let stripe =
+            "t=1700000000,v1=33ff6664879612e191c4e01184f3da32597a3c9b33ca2655fac317d01c5abc3f";
+        let stripe = SignatureConfig {
+            tolerance,
+            ..config(SignatureScheme::Stripe, "whsec_test")
+        }
+        .verify(&request(&[("Stripe-Signature", stripe)], BODY), at(now));
+
+        let standard = SignatureConfig {
+            tolerance,
+            ..config(SignatureScheme::StandardWebhooks, STANDARD_SECRET)
+        }
+        .verify(
+            &request(
+                &[
+                    ("webhook-id", "msg_1"),
+                    ("webhook-timestamp", "1700000000"),
+                    ("webhook-signature", STANDARD_SIGNATURE),
+                ],
+                BODY,
+            ),
+            at(now),
+        );
+
+        [stripe, standard]
+    }
+
+    #[test]
+    fn timestamp_at_the_tolerance_is_accepted() {
+        for now in [SIGNED_AT - 300, SIGNED_AT + 300] {
+            for check in verify_timestamps(300, now) {
+                assert!(check.valid, "{}: {}", check.scheme, check.reason);
+            }
+        }
+    }
+
+    #[test]
+    fn timestamp_past_the_tolerance_is_rejected() {
+        for check in verify_timestamps(300, SIGNED_AT + 301) {
+            assert!(!check.valid, "{} accepted an old timestamp", check.scheme);
+            assert_eq!(
+                check.reason,
+                "timestamp is 301 s old, outside the 300 s tolerance"
+            );
+        }
+        for check in verify_timestamps(300, SIGNED_AT - 301) {
+            assert!(!check.valid, "{} accepted a future timestamp", check.scheme);
+            assert_eq!(
+                check.reason,
+                "timestamp is 301 s in the future, outside the 300 s tolerance"
+            );
+        }
+    }
+
+    #[test]
+    fn zero_tolerance_accepts_any_timestamp() {
+        for check in verify_timestamps(0, SIGNED_AT + 10 * 365 * 86_400) {
+            assert!(check.valid, "{}: {}", check.scheme, check.reason);
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::request::{CapturedRequest, RequestBody};
+
+/// Ways of writing a captured request out as code that sends it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetFormat {
+    Curl,
+    Httpie,
+    Fetch,
+    Python,
+}
+
+impl SnippetFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SnippetFormat::Curl => "curl",
+            SnippetFormat::Httpie => "HTTPie",
+            SnippetFormat::Fetch => "fetch",
+            SnippetFormat::Python => "Python requests",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            SnippetFormat::Curl => SnippetFormat::Httpie,
+            SnippetFormat::Httpie => SnippetFormat::Fetch,
+            SnippetFormat::Fetch => SnippetFormat::Python,
+            SnippetFormat::Python => SnippetFormat::Curl,
+        }
+    }
+}
+
+/// Headers the client computes itself, or that the URL already carries.
+const SKIPPED_HEADERS: [&str; 4] = ["host", "content-length", "connection", "transfer-encoding"];
+
+/// Render `request` in `format`, aimed at `listening_address`, or with
+/// `use_host`, at the request's `Host` header. Headers keep their captured
+/// order and their exact bytes. Bodies are sent exactly as received, so
+/// compressed or binary payloads are embedded as base64.
+pub fn render(
+    request: &CapturedRequest,
+    format: SnippetFormat,
+    listening_address: &str,
+    use_host: bool,
+) -> String {
+    let host = match request.header("host") {
+        Some(host) if use_host => host,
+        _ => listening_address,
+    };
+    let url = format!("http://{}{}", host, request.full_path());
+
+    let headers: Vec<(&str, Vec<u8>)> = request
+        .headers
+        .iter()
+        .filter(|(k, _)| !SKIPPED_HEADERS.contains(&k.to_ascii_lowercase().as_str()))
+        .map(|(k, v)| (k, v.to_vec()))
+        .collect();
+
+    match format {
+        SnippetFormat::Curl => curl(&request.method, &url, &headers, &request.body),
+        SnippetFormat::Httpie => httpie(&request.method, &url, &headers, &request.body),
+        SnippetFormat::Fetch => fetch(&request.method, &url, &combine(&headers), &request.body),
+        SnippetFormat::Python => python(&request.method, &url, &combine(&headers), &request.body),
+    }
+}
+
+/// Join repeated headers into one, for clients that take headers as an
+/// object and would otherwise keep only the last.
+fn combine<'a>(headers: &[(&'a str, Vec<u8>)]) -> Vec<(&'a str, Vec<u8>)> {
+    let mut combined: Vec<(&'a str, Vec<u8>)> = Vec::new();
+    for (name, value) in headers {
+        match combined
+            .iter_mut()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            Some((_, existing)) => {
+                existing.extend_from_slice(b", ");
+                existing.extend_from_slice(value);
+            }
+            None => combined.push((name, value.clone())),
+        }
+    }
+    combined
+}
+
+fn curl(method: &str, url: &str, headers: &[(&str, Vec<u8>)], body: &RequestBody) -> String {
+    let mut args = Vec::new();
+    match method {
+        "GET" if body.is_empty() => {}
+        // `-X HEAD` would wait for a body that never comes
+        "HEAD" => args.push("--head".to_string()),
+        _ => args.push(format!("-X {}", shell_quote(method))),
+    }
+    args.push(shell_quote(url));
+    for (name, value) in headers {
+        // curl drops a header given as `Name:`; `Name;` sends it empty
+        let header = match value.is_empty() {
+            true => format!("{};", name).into_bytes(),
+            false => [name.as_bytes(), b": ", value].concat(),
+        };
+        args.push(format!("-H {}", shell_bytes(&header)));
+    }
+
+    let mut command = String::new();
+    match body {
+        RequestBody::Empty => {}
+        RequestBody::Utf8(text) => args.push(format!("--data-raw {}", shell_quote(text))),
+        RequestBody::Binary(bytes) => {
+            command = base64_pipe(bytes);
+            args.push("--data-binary @-".to_string());
+        }
+    }
+
+    command.push_str("curl ");
+    command.push_str(&args.join(" \\\n  "));
+    command
+}
+
+fn httpie(method: &str, url: &str, headers: &[(&str, Vec<u8>)], body: &RequestBody) -> String {
+    let mut args = vec![shell_quote(method), shell_quote(url)];
+    for (name, value) in headers {
+        // As with curl, `Name:` alone would drop the header
+        let header = match value.is_empty() {
+            true => format!("{};", name).into_bytes(),
+            false => [name.as_bytes(), b":", value].concat(),
+        };
+        args.push(shell_bytes(&header));
+    }
+
+    let mut command = String::new();
+    match body {
+        RequestBody::Empty => {}
+        RequestBody::Utf8(text) => args.push(format!("--raw {}", shell_quote(text))),
+        RequestBody::Binary(bytes) => command = base64_pipe(bytes),
+    }
+
+    command.push_str("http ");
+    command.push_str(&args.join(" \\\n  "));
+    command
+}
+
+fn fetch(method: &str, url: &str, headers: &[(&str, Vec<u8>)], body: &RequestBody) -> String {
+    let mut options = vec![format!("  method: {},", string_literal(method))];
+
+    if !headers.is_empty() {
+        let lines: Vec<_> = headers
+            .iter()
+            .map(|(name, value)| format!("    {}: {},", string_literal(name), byte_literal(value)))
+            .collect();
+        options.push(format!("  headers: {{\n{}\n  }},", lines.join("\n")));
+    }
+
+    match body {
+        RequestBody::Empty => {}
+        RequestBody::Utf8(text) => options.push(format!("  body: {},", string_literal(text))),
+        RequestBody::Binary(bytes) => options.push(format!(
+            "  body: Uint8Array.from(atob({}), (c) => c.charCodeAt(0)),",
+            string_literal(&BASE64.encode(bytes))
+        )),
+    }
+
+    format!(
+        "const response = await fetch({}, {{\n{}\n}});\nconsole.log(response.status, await response.text());",
+        string_literal(url),
+        options.join("\n")
+    )
+}
+
+fn python(method: &str, url: &str, headers: &[(&str, Vec<u8>)], body: &RequestBody) -> String {
+    let mut imports = vec!["import requests"];
+    let mut args = vec![
+        format!("    {},", string_literal(method)),
+        format!("    {},", string_literal(url)),
+    ];
+
+    if !headers.is_empty() {
+        let lines: Vec<_> = headers
+            .iter()
+            .map(|(name, value)| {
+                format!("        {}: {},", string_literal(name), byte_literal(value))
+            })
+            .collect();
+        args.push(format!("    headers={{\n{}\n    }},", lines.join("\n")));
+    }
+
+    match body {
+        RequestBody::Empty => {}
+        // requests would encode a str body as Latin-1
+        RequestBody::Utf8(text) => {
+            args.push(format!("    data={}.encode(),", string_literal(text)))
+        }
+        RequestBody::Binary(bytes) => {
+            imports.insert(0, "import base64");
+            args.push(format!(
+                "    data=base64.b64decode({}),",
+                string_literal(&BASE64.encode(bytes))
+            ));
+        }
+    }
+
+    format!(
+        "{}\n\nresponse = requests.request(\n{}\n)\nprint(response.status_code, response.text)",
+        imports.join("\n"),
+        args.join("\n")
+    )
+}
+
+/// `echo <base64> | base64 -d | `, to feed binary bodies on stdin.
+fn base64_pipe(bytes: &[u8]) -> String {
+    format!("echo {} | base64 -d | ", BASE64.encode(bytes))
+}
+
+/// Quote for POSIX shells, leaving simple words bare.
+fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// Quote raw bytes for the shell. Text is quoted as usual; anything else
+/// uses `$'...'` with `\xNN` escapes, so the exact bytes reach the command.
+fn shell_bytes(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => shell_quote(text),
+        Err(_) => format!("$'{}'", escape_bytes(bytes, '\'')),
+    }
+}
+
+/// A double-quoted string literal valid in both JavaScript and Python.
+fn string_literal(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// A string literal for a header value, valid in both JavaScript and
+/// Python. fetch and Python's `http.client` send each character of a header
+/// as one byte, so every byte outside printable ASCII is written as `\xNN`
+/// to send the original bytes, UTF-8 or not.
+fn byte_literal(bytes: &[u8]) -> String {
+    format!("\"{}\"", escape_bytes(bytes, '"'))
+}
+
+/// Printable ASCII as is, with `quote` and `\` backslash-escaped, and any
+/// other byte as `\xNN`.
+fn escape_bytes(bytes: &[u8], quote: char) -> String {
+    let mut escaped = String::new();
+    for &byte in bytes {
+        match byte as char {
+            c if c == quote || c == '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c @ ' '..='~' => escaped.push(c),
+            _ => escaped.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    escaped
+}
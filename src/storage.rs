@@ -0,0 +1,213 @@
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+use crate::event::AppEvent;
+use crate::logger;
+use crate::request::{CapturedRequest, UpstreamResult};
+
+/// A named, append-only JSON Lines file of captured requests.
+pub struct Session {
+    pub name: String,
+    file: File,
+    /// Latest copy of every request in the session, so results that arrive
+    /// later can be written out as a full record.
+    requests: HashMap<u64, CapturedRequest>,
+}
+
+/// Summary of a stored session, for listing.
+pub struct SessionInfo {
+    pub name: String,
+    pub requests: usize,
+    pub modified: Option<DateTime<Local>>,
+}
+
+/// Requests read back from a session file.
+pub struct History {
+    /// Oldest first.
+    pub requests: Vec<CapturedRequest>,
+    /// Lines that could not be parsed, e.g. one cut short by a crash.
+    pub skipped: usize,
+}
+
+impl Session {
+    /// Directory holding named sessions.
+    pub fn dir() -> PathBuf {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("webhook/sessions")
+    }
+
+    /// Resolve a session name to its file. Names that look like paths
+    /// (containing a separator or ending in `.jsonl`) are used as is.
+    pub fn path_for(name: &str) -> PathBuf {
+        if name.contains(std::path::MAIN_SEPARATOR)
+            || name.contains('/')
+            || name.ends_with(".jsonl")
+        {
+            PathBuf::from(name)
+        } else {
+            Self::dir().join(format!("{}.jsonl", name))
+        }
+    }
+
+    /// Open a session for recording, creating it if needed, and return the
+    /// requests it already holds.
+    pub fn open(name: &str) -> io::Result<(Self, History)> {
+        let path = Self::path_for(name);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let history = if path.exists() {
+            Self::read(&path)?
+        } else {
+            History {
+                requests: Vec::new(),
+                skipped: 0,
+            }
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let session = Self {
+            name: name.to_string(),
+            file,
+            requests: history.requests.iter().map(|r| (r.id, r.clone())).collect(),
+        };
+
+        Ok((session, history))
+    }
+
+    /// Read the requests stored in a session file. A request is written
+    /// again when its response or an upstream result is known; the last
+    /// copy wins but keeps the position of the first, and the results of
+    /// earlier copies.
+    pub fn read(path: &Path) -> io::Result<History> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut history = History {
+            requests: Vec::new(),
+            skipped: 0,
+        };
+        let mut positions = HashMap::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<CapturedRequest>(&line) {
+                Ok(mut request) => match positions.get(&request.id) {
+                    Some(&i) => {
+                        request.merge_results(&history.requests[i]);
+                        history.requests[i] = request;
+                    }
+                    None => {
+                        positions.insert(request.id, history.requests.len());
+                        history.requests.push(request);
+                    }
+                },
+                Err(_) => history.skipped += 1,
+            }
+        }
+
+        Ok(history)
+    }
+
+    /// Append a request as one line, flushed straight away. Results already
+    /// stored for it are carried over.
+    pub fn append(&mut self, request: &CapturedRequest) -> io::Result<()> {
+        let mut request = request.clone();
+        if let Some(stored) = self.requests.get(&request.id) {
+            request.merge_results(stored);
+        }
+
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.file.flush()?;
+
+        self.requests.insert(request.id, request);
+        Ok(())
+    }
+
+    pub fn append_forward(&mut self, id: u64, result: &UpstreamResult) -> io::Result<()> {
+        self.update(id, |request| request.forwards.push(result.clone()))
+    }
+
+    pub fn append_replay(&mut self, id: u64, result: &UpstreamResult) -> io::Result<()> {
+        self.update(id, |request| request.replays.push(result.clone()))
+    }
+
+    /// Change a request and append the updated record. Requests not in the
+    /// session are ignored.
+    fn update(&mut self, id: u64, change: impl FnOnce(&mut CapturedRequest)) -> io::Result<()> {
+        let Some(request) = self.requests.get(&id) else {
+            return Ok(());
+        };
+        let mut request = request.clone();
+        change(&mut request);
+        self.append(&request)
+    }
+
+    /// All named sessions in the sessions directory, most recent first.
+    pub fn list() -> io::Result<Vec<SessionInfo>> {
+        let dir = Self::dir();
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut sessions = Vec::new();
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+
+            let requests = Self::read(&path).map(|h| h.requests.len()).unwrap_or(0);
+            let modified = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .map(DateTime::<Local>::from);
+
+            sessions.push(SessionInfo {
+                name: name.to_string(),
+                requests,
+                modified,
+            });
+        }
+
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.modified));
+        Ok(sessions)
+    }
+}
+
+/// Record every captured request to `session`, passing all events on to
+/// `tx` for the UI or headless output.
+pub async fn record(
+    mut session: Session,
+    mut rx: mpsc::UnboundedReceiver<AppEvent>,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) {
+    while let Some(event) = rx.recv().await {
+        let saved = match &event {
+            AppEvent::Request(request) | AppEvent::Responded(request) => session.append(request),
+            AppEvent::Mirrored(id, result) => session.append_forward(*id, result),
+            AppEvent::Replayed(id, result) => session.append_replay(*id, result),
+            _ => Ok(()),
+        };
+        if let Err(e) = saved {
+            let message = format!("failed to save to session {}: {}", session.name, e);
+            logger::write(&message);
+            let _ = tx.send(AppEvent::StorageError(message));
+        }
+        if tx.send(event).is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::Utc;
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, Output, RenderContext, RenderError,
+    RenderErrorReason, TemplateErrorReason,
+};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::request::CapturedRequest;
+
+/// Handlebars registry shared by all response templates. Output is not
+/// HTML-escaped, since responses are usually JSON or plain text.
+fn registry() -> &'static Handlebars<'static> {
+    static REGISTRY: OnceLock<Handlebars<'static>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut hb = Handlebars::new();
+        hb.register_escape_fn(handlebars::no_escape);
+        hb.register_helper("uuid", Box::new(uuid_helper));
+        hb.register_helper("now", Box::new(now_helper));
+        hb.register_helper("timestamp", Box::new(timestamp_helper));
+        hb.register_helper("json", Box::new(json_helper));
+        hb
+    })
+}
+
+/// Build the data a response template is rendered against.
+pub fn context(request: &CapturedRequest, params: &HashMap<String, String>) -> Value {
+    let mut query = Map::new();
+    for (key, value) in request.query_params() {
+        query.entry(key).or_insert(Value::String(value));
+    }
+
+    let headers: Map<String, Value> = request
+        .headers
+        .names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = request.headers.combined(name)?;
+            Some((name.to_ascii_lowercase(), Value::String(value)))
+        })
+        .collect();
+
+    let body = request.display_body();
+
+    json!({
+        "id": request.id,
+        "method": request.method,
+        "path": request.path,
+        "params": params,
+        "query": query,
+        "headers": headers,
+        "body": String::from_utf8_lossy(body.as_bytes()),
+        "json": request.json_body(),
+        "timestamp": request.timestamp.to_rfc3339(),
+    })
+}
+
+/// Render `template` against `context`. Text without `{{` is returned as is.
+pub fn render(template: &str, context: &Value) -> Result<String, String> {
+    if !template.contains("{{") {
+        return Ok(template.to_string());
+    }
+    registry()
+        .render_template(template, context)
+        .map_err(|e| describe_error(&e))
+}
+
+/// Handlebars syntax errors list every token the parser expected; reduce
+/// them to a one-line message with the position.
+fn describe_error(error: &RenderError) -> String {
+    let RenderErrorReason::TemplateError(template_error) = error.reason() else {
+        return match (error.line_no, error.column_no) {
+            (Some(line), Some(column)) => {
+                format!("{} at line {} column {}", error.reason(), line, column)
+            }
+            _ => error.reason().to_string(),
+        };
+    };
+
+    let reason = match template_error.reason() {
+        TemplateErrorReason::InvalidSyntax(_) => "invalid template syntax".to_string(),
+        other => other.to_string(),
+    };
+
+    match template_error.pos() {
+        Some((line, column)) => format!("{} at line {} column {}", reason, line, column),
+        None => reason,
+    }
+}
+
+/// Check that `template` parses, without needing a real request.
+pub fn validate(template: &str) -> Result<(), String> {
+    render(template, &Value::Null).map(|_| ())
+}
+
+/// `{{uuid}}` - a random v4 UUID.
+fn uuid_helper(
+    _: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    out.write(&uuid::Uuid::new_v4().to_string())?;
+    Ok(())
+}
+
+/// `{{now}}` - the current time as RFC 3339, or `{{now "%Y-%m-%d"}}` with a
+/// strftime format.
+fn now_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let now = Utc::now();
+    let text = match h.param(0).and_then(|p| p.value().as_str()) {
+        Some(format) => {
+            // Formatting panics on unknown specifiers, so check them first
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(RenderErrorReason::Other(format!(
+                    "now: invalid strftime format {:?}",
+                    format
+                ))
+                .into());
+            }
+            now.format(format).to_string()
+        }
+        None => now.to_rfc3339(),
+    };
+    out.write(&text)?;
+    Ok(())
+}
+
+/// `{{timestamp}}` - the current Unix time in seconds, or milliseconds with
+/// `{{timestamp "ms"}}`.
+fn timestamp_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let now = Utc::now();
+    let value = match h.param(0).and_then(|p| p.value().as_str()) {
+        Some("ms") => now.timestamp_millis(),
+        _ => now.timestamp(),
+    };
+    out.write(&value.to_string())?;
+    Ok(())
+}
+
+/// `{{json value}}` - the value serialized as JSON, e.g. `{{json json.data}}`.
+fn json_helper(
+    h: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let value = h
+        .param(0)
+        .ok_or(RenderErrorReason::ParamNotFoundForIndex("json", 0))?
+        .value();
+    out.write(&value.to_string())?;
+    Ok(())
+}
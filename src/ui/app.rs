@@ -1,11 +1,26 @@
-use crate::request::CapturedRequest;
+use crate::config::SharedConfig;
+use crate::event::AppEvent;
+use crate::har;
+use crate::request::{CapturedRequest, UpstreamResult};
+use crate::snippet::{self, SnippetFormat};
+use crate::ui::clipboard;
+use crate::upstream;
+use chrono::Local;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
-use std::time::Duration;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 pub enum InputEvent {
     Key(KeyCode),
-    NewRequest(CapturedRequest),
+    NewRequest(Box<CapturedRequest>),
+    UpdateRequest(Box<CapturedRequest>),
+    Mirrored(u64, Box<UpstreamResult>),
+    Replayed(u64, Box<UpstreamResult>),
+    ConfigReloaded(String),
+    ConfigError(String),
+    StorageError(String),
     Tick,
 }
 
@@ -16,19 +31,73 @@ pub struct App {
     pub detail_scroll: usize,
     pub should_quit: bool,
     pub listening_address: String,
+    /// Path of the loaded config file, or a note that defaults are in use.
+    pub config_source: String,
+    /// Name of the session requests are being saved to, if any.
+    pub session: Option<String>,
     pub body_expanded: bool,
+    pub hex_view: bool,
+    pub wire_view: bool,
+    /// Show headers in the order received instead of sorted by name.
+    pub received_order: bool,
+    pub status_message: Option<StatusMessage>,
+    /// Why the config file last failed to reload. Unlike status messages it
+    /// stays up, whenever no other message is shown, until a reload works.
+    pub config_error: Option<String>,
+    /// What `y` copies the selected request as.
+    pub copy_format: SnippetFormat,
+    /// Aim copied requests at their `Host` header instead of this server.
+    pub copy_uses_host: bool,
+    /// Ids of requests marked for replay.
+    pub marked: HashSet<u64>,
+    config: SharedConfig,
+    /// Where replay results are sent, to come back in as events.
+    events: mpsc::UnboundedSender<AppEvent>,
+}
+
+/// How long a status message stays up.
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A transient note shown in the status bar.
+pub struct StatusMessage {
+    pub text: String,
+    pub is_error: bool,
+    pub shown_at: Instant,
 }
 
 impl App {
-    pub fn new(listening_address: String) -> Self {
+    pub fn new(
+        listening_address: String,
+        config_source: String,
+        session: Option<String>,
+        history: Vec<CapturedRequest>,
+        config: SharedConfig,
+        events: mpsc::UnboundedSender<AppEvent>,
+    ) -> Self {
+        // History is stored oldest first; the list shows newest first
+        let mut requests = history;
+        requests.reverse();
+
         Self {
-            requests: Vec::new(),
+            requests,
             selected_index: 0,
             scroll_offset: 0,
             detail_scroll: 0,
             should_quit: false,
             listening_address,
+            config_source,
+            session,
             body_expanded: false,
+            hex_view: false,
+            wire_view: false,
+            received_order: false,
+            status_message: None,
+            config_error: None,
+            copy_format: SnippetFormat::Curl,
+            copy_uses_host: false,
+            marked: HashSet::new(),
+            config,
+            events,
         }
     }
 
@@ -41,6 +110,15 @@ impl App {
         }
     }
 
+    /// Replace a listed request with a newer copy, e.g. once its response
+    /// is known. Requests cleared from the list are not brought back.
+    pub fn update_request(&mut self, mut request: CapturedRequest) {
+        if let Some(existing) = self.requests.iter_mut().find(|r| r.id == request.id) {
+            request.merge_results(existing);
+            *existing = request;
+        }
+    }
+
     pub fn selected_request(&self) -> Option<&CapturedRequest> {
         self.requests.get(self.selected_index)
     }
@@ -69,8 +147,10 @@ impl App {
         self.detail_scroll += 1;
     }
 
+    /// Clear the list. Requests already saved to a session stay on disk.
     pub fn clear_requests(&mut self) {
         self.requests.clear();
+        self.marked.clear();
         self.selected_index = 0;
         self.scroll_offset = 0;
         self.detail_scroll = 0;
@@ -80,6 +160,182 @@ impl App {
         self.body_expanded = !self.body_expanded;
     }
 
+    pub fn toggle_hex_view(&mut self) {
+        self.hex_view = !self.hex_view;
+        self.detail_scroll = 0;
+    }
+
+    pub fn toggle_wire_view(&mut self) {
+        self.wire_view = !self.wire_view;
+        self.detail_scroll = 0;
+    }
+
+    pub fn toggle_header_order(&mut self) {
+        self.received_order = !self.received_order;
+    }
+
+    /// Export the selected request, or every listed request, to a HAR file
+    /// in the current directory.
+    pub fn export_har(&mut self, all: bool) {
+        let (requests, path): (Vec<_>, _) = if all {
+            let name = format!("webhook-{}.har", Local::now().format("%Y%m%d-%H%M%S"));
+            (self.requests.iter().rev().collect(), PathBuf::from(name))
+        } else {
+            let Some(request) = self.selected_request() else {
+                return;
+            };
+            (
+                vec![request],
+                PathBuf::from(format!("webhook-{}.har", request.id)),
+            )
+        };
+
+        if requests.is_empty() {
+            return;
+        }
+        let count = requests.len();
+        match har::write(&path, &requests) {
+            Ok(()) => self.set_status(
+                format!(
+                    "exported {} request{} to {}",
+                    count,
+                    if count == 1 { "" } else { "s" },
+                    path.display()
+                ),
+                false,
+            ),
+            Err(e) => self.set_status(format!("export to {} failed: {}", path.display(), e), true),
+        }
+    }
+
+    /// Copy the selected request to the clipboard as a command that sends
+    /// it again.
+    pub fn copy_selected(&mut self) {
+        let Some(request) = self.selected_request() else {
+            return;
+        };
+        let snippet = snippet::render(
+            request,
+            self.copy_format,
+            &self.listening_address,
+            self.copy_uses_host,
+        );
+        match clipboard::copy(&snippet) {
+            Ok(()) => self.set_status(
+                format!("copied request as {}", self.copy_format.label()),
+                false,
+            ),
+            Err(e) => self.set_status(format!("copy failed: {}", e), true),
+        }
+    }
+
+    pub fn cycle_copy_format(&mut self) {
+        self.copy_format = self.copy_format.next();
+        self.set_status(format!("y copies as {}", self.copy_format.label()), false);
+    }
+
+    pub fn toggle_copy_host(&mut self) {
+        self.copy_uses_host = !self.copy_uses_host;
+        let target = match self.copy_uses_host {
+            true => "the request's Host header",
+            false => "this server",
+        };
+        self.set_status(format!("y sends copies to {}", target), false);
+    }
+
+    pub fn toggle_mark(&mut self) {
+        if let Some(id) = self.selected_request().map(|r| r.id) {
+            if !self.marked.remove(&id) {
+                self.marked.insert(id);
+            }
+        }
+    }
+
+    /// Replay the marked requests, oldest first, or the selected one if none
+    /// are marked. Results arrive later as [`InputEvent::Replayed`].
+    pub fn replay(&mut self) {
+        let Some(target) = self.config.read().unwrap().replay.upstream() else {
+            self.set_status(
+                "no replay target; set replay.target in the config or pass --replay-to".to_string(),
+                true,
+            );
+            return;
+        };
+
+        let requests: Vec<CapturedRequest> = if self.marked.is_empty() {
+            self.selected_request().cloned().into_iter().collect()
+        } else {
+            self.requests
+                .iter()
+                .rev()
+                .filter(|r| self.marked.contains(&r.id))
+                .cloned()
+                .collect()
+        };
+        if requests.is_empty() {
+            return;
+        }
+
+        self.set_status(
+            format!(
+                "replaying {} request{} to {}",
+                requests.len(),
+                if requests.len() == 1 { "" } else { "s" },
+                target.target
+            ),
+            false,
+        );
+
+        // One at a time, so the target sees them in their original order
+        let events = self.events.clone();
+        tokio::spawn(async move {
+            for request in requests {
+                let result = upstream::send(&request, &target).await;
+                if events
+                    .send(AppEvent::Replayed(request.id, Box::new(result)))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    pub fn add_forward(&mut self, id: u64, result: UpstreamResult) {
+        if let Some(request) = self.requests.iter_mut().find(|r| r.id == id) {
+            request.forwards.push(result);
+        }
+    }
+
+    pub fn add_replay(&mut self, id: u64, result: UpstreamResult) {
+        self.set_status(
+            format!("replayed #{}: {}", id, result.summary()),
+            result.response.is_none(),
+        );
+        if let Some(request) = self.requests.iter_mut().find(|r| r.id == id) {
+            request.replays.push(result);
+        }
+    }
+
+    pub fn set_status(&mut self, text: String, is_error: bool) {
+        self.status_message = Some(StatusMessage {
+            text,
+            is_error,
+            shown_at: Instant::now(),
+        });
+    }
+
+    /// Clear the status message once it has been up for long enough.
+    fn expire_status(&mut self) {
+        if self
+            .status_message
+            .as_ref()
+            .is_some_and(|m| m.shown_at.elapsed() >= STATUS_TIMEOUT)
+        {
+            self.status_message = None;
+        }
+    }
+
     pub fn handle_input(&mut self, event: InputEvent) {
         match event {
             InputEvent::Key(key) => match key {
@@ -88,6 +344,16 @@ impl App {
                 KeyCode::Up | KeyCode::Char('k') => self.move_selection_up(),
                 KeyCode::Down | KeyCode::Char('j') => self.move_selection_down(),
                 KeyCode::Enter => self.toggle_body_expanded(),
+                KeyCode::Char('x') => self.toggle_hex_view(),
+                KeyCode::Char('w') => self.toggle_wire_view(),
+                KeyCode::Char('o') => self.toggle_header_order(),
+                KeyCode::Char('e') => self.export_har(false),
+                KeyCode::Char('E') => self.export_har(true),
+                KeyCode::Char('y') => self.copy_selected(),
+                KeyCode::Char('Y') => self.cycle_copy_format(),
+                KeyCode::Char('H') => self.toggle_copy_host(),
+                KeyCode::Char(' ') => self.toggle_mark(),
+                KeyCode::Char('r') => self.replay(),
                 KeyCode::PageUp => {
                     for _ in 0..5 {
                         self.scroll_detail_up();
@@ -100,16 +366,36 @@ impl App {
                 }
                 _ => {}
             },
-            InputEvent::NewRequest(req) => self.add_request(req),
-            InputEvent::Tick => {}
+            InputEvent::NewRequest(req) => self.add_request(*req),
+            InputEvent::UpdateRequest(req) => self.update_request(*req),
+            InputEvent::Mirrored(id, result) => self.add_forward(id, *result),
+            InputEvent::Replayed(id, result) => self.add_replay(id, *result),
+            InputEvent::ConfigReloaded(message) => {
+                self.config_error = None;
+                self.set_status(message, false);
+            }
+            InputEvent::ConfigError(message) => {
+                self.config_error = Some(message);
+                self.status_message = None;
+            }
+            InputEvent::StorageError(message) => self.set_status(message, true),
+            InputEvent::Tick => self.expire_status(),
         }
     }
 }
 
-pub async fn poll_events(rx: &mut mpsc::UnboundedReceiver<CapturedRequest>) -> Option<InputEvent> {
-    // Check for new requests first (non-blocking)
-    if let Ok(request) = rx.try_recv() {
-        return Some(InputEvent::NewRequest(request));
+pub async fn poll_events(rx: &mut mpsc::UnboundedReceiver<AppEvent>) -> Option<InputEvent> {
+    // Check for server events first (non-blocking)
+    if let Ok(event) = rx.try_recv() {
+        return Some(match event {
+            AppEvent::Request(request) => InputEvent::NewRequest(request),
+            AppEvent::Responded(request) => InputEvent::UpdateRequest(request),
+            AppEvent::Mirrored(id, result) => InputEvent::Mirrored(id, result),
+            AppEvent::Replayed(id, result) => InputEvent::Replayed(id, result),
+            AppEvent::ConfigReloaded(message) => InputEvent::ConfigReloaded(message),
+            AppEvent::ConfigError(message) => InputEvent::ConfigError(message),
+            AppEvent::StorageError(message) => InputEvent::StorageError(message),
+        });
     }
 
     // Poll for keyboard events with timeout
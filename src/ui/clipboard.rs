@@ -0,0 +1,11 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::io::{self, Write};
+
+/// Put `text` on the system clipboard with an OSC 52 escape sequence. The
+/// terminal does the copying, so this also works over SSH; tmux needs
+/// `set -g set-clipboard on`.
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut out = io::stdout();
+    write!(out, "\x1b]52;c;{}\x07", BASE64.encode(text))?;
+    out.flush()
+}
@@ -1,4 +1,5 @@
 pub mod app;
+pub mod clipboard;
 pub mod render;
 
 use app::{poll_events, App};
@@ -10,11 +11,17 @@ use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io::{self, stdout};
 use tokio::sync::mpsc;
 
-use crate::request::CapturedRequest;
+use crate::config::SharedConfig;
+use crate::event::AppEvent;
+use crate::storage::History;
 
 pub async fn run_tui(
     listening_address: String,
-    mut rx: mpsc::UnboundedReceiver<CapturedRequest>,
+    config_source: String,
+    session: Option<(String, History)>,
+    config: SharedConfig,
+    events: mpsc::UnboundedSender<AppEvent>,
+    mut rx: mpsc::UnboundedReceiver<AppEvent>,
 ) -> io::Result<()> {
     // Setup terminal
     enable_raw_mode()?;
@@ -24,7 +31,49 @@ pub async fn run_tui(
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(listening_address);
+    let mut app = match session {
+        Some((name, history)) => {
+            let restored = history.requests.len();
+            let mut app = App::new(
+                listening_address,
+                config_source,
+                Some(name.clone()),
+                history.requests,
+                config,
+                events,
+            );
+            if history.skipped > 0 {
+                app.set_status(
+                    format!(
+                        "session {}: skipped {} unreadable line{}",
+                        name,
+                        history.skipped,
+                        if history.skipped == 1 { "" } else { "s" }
+                    ),
+                    true,
+                );
+            } else if restored > 0 {
+                app.set_status(
+                    format!(
+                        "restored {} request{} from session {}",
+                        restored,
+                        if restored == 1 { "" } else { "s" },
+                        name
+                    ),
+                    false,
+                );
+            }
+            app
+        }
+        None => App::new(
+            listening_address,
+            config_source,
+            None,
+            Vec::new(),
+            config,
+            events,
+        ),
+    };
 
     // Main loop
     loop {
@@ -1,3 +1,5 @@
+use axum::http::StatusCode;
+use chrono::Local;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -6,6 +8,8 @@ use ratatui::{
     Frame,
 };
 
+use crate::cloudevents::{CloudEvents, Mode, CORE_ATTRIBUTES};
+use crate::request::{CapturedResponse, Headers, UpstreamResult};
 use crate::ui::app::App;
 
 pub fn render(frame: &mut Frame, app: &App) {
@@ -47,7 +51,14 @@ fn render_request_list(frame: &mut Frame, app: &App, area: Rect) {
                 _ => Color::White,
             };
 
-            let content = Line::from(vec![
+            let mark = if app.marked.contains(&req.id) {
+                " *"
+            } else {
+                "  "
+            };
+
+            let mut spans = vec![
+                Span::styled(mark, Style::default().fg(Color::Magenta)),
                 Span::styled(
                     format!("{} ", req.timestamp_display()),
                     Style::default().fg(Color::DarkGray),
@@ -56,8 +67,23 @@ fn render_request_list(frame: &mut Frame, app: &App, area: Rect) {
                     format!("{:6} ", req.method),
                     Style::default().fg(method_color),
                 ),
-                Span::styled(req.path.clone(), style),
-            ]);
+            ];
+            if let Some(provider) = &req.provider {
+                spans.push(Span::styled(
+                    format!("{} ", provider.label()),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            spans.push(Span::styled(req.path.clone(), style));
+            if let Some(check) = &req.signature {
+                let (badge, color) = if check.valid {
+                    (" sig ok", Color::Green)
+                } else {
+                    (" sig bad", Color::Red)
+                };
+                spans.push(Span::styled(badge, Style::default().fg(color)));
+            }
+            let content = Line::from(spans);
 
             ListItem::new(content)
         })
@@ -102,6 +128,23 @@ fn render_request_details(frame: &mut Frame, app: &App, area: Rect) {
     if let Some(request) = app.selected_request() {
         let mut lines: Vec<Line> = Vec::new();
 
+        // What the request is, when it came from a known provider
+        if let Some(provider) = &request.provider {
+            let mut summary = vec![Span::styled(
+                provider.label(),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            if let Some(delivery) = &provider.delivery {
+                summary.push(Span::styled(
+                    format!("  delivery {}", delivery),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            lines.push(Line::from(summary));
+        }
+
         // Timestamp
         lines.push(Line::from(vec![
             Span::styled("Timestamp: ", Style::default().fg(Color::Gray)),
@@ -131,37 +174,154 @@ fn render_request_details(frame: &mut Frame, app: &App, area: Rect) {
             Span::styled(request.full_path(), Style::default().fg(Color::White)),
         ]));
 
+        if let Some(connection) = &request.connection {
+            lines.push(Line::from(vec![
+                Span::styled("Client: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format!(
+                        "{} -> {} ({})",
+                        connection.remote_addr, connection.local_addr, connection.http_version
+                    ),
+                    Style::default().fg(Color::White),
+                ),
+            ]));
+        }
+        if let Some(client) = &request.apparent_client {
+            lines.push(Line::from(vec![
+                Span::styled("Apparent client: ", Style::default().fg(Color::Gray)),
+                Span::styled(client, Style::default().fg(Color::White)),
+                Span::styled(
+                    " (from forwarding headers)",
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+        if let Some(check) = &request.signature {
+            let (verdict, color) = if check.valid {
+                ("valid", Color::Green)
+            } else {
+                ("invalid", Color::Red)
+            };
+            lines.push(Line::from(vec![
+                Span::styled("Signature: ", Style::default().fg(Color::Gray)),
+                Span::styled(
+                    format!("{} {}", check.scheme, verdict),
+                    Style::default().fg(color),
+                ),
+                Span::styled(
+                    format!(" ({})", check.reason),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+        }
+
         lines.push(Line::from(""));
 
+        if let Some(cloudevents) = &request.cloudevents {
+            push_cloudevents(&mut lines, cloudevents);
+            lines.push(Line::from(""));
+        }
+
         // Headers
-        lines.push(Line::from(Span::styled(
-            "Headers:",
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )));
+        lines.push(Line::from(vec![
+            Span::styled(
+                "Headers:",
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            Span::styled(
+                if app.received_order {
+                    " (as received)"
+                } else {
+                    " (sorted)"
+                },
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]));
 
-        let mut headers: Vec<_> = request.headers.iter().collect();
-        headers.sort_by(|a, b| a.0.cmp(b.0));
+        let headers: Vec<_> = if app.received_order {
+            request.headers.iter().collect()
+        } else {
+            request.headers.sorted()
+        };
 
         for (key, value) in headers {
             lines.push(Line::from(vec![
                 Span::styled(format!("  {}: ", key), Style::default().fg(Color::Yellow)),
-                Span::styled(value, Style::default().fg(Color::White)),
+                Span::styled(Headers::text(value), Style::default().fg(Color::White)),
             ]));
         }
 
         lines.push(Line::from(""));
 
         // Body
-        lines.push(Line::from(Span::styled(
+        let shown_body = if app.wire_view {
+            &request.body
+        } else {
+            request.display_body()
+        };
+
+        let mut body_title = vec![Span::styled(
             "Body:",
             Style::default()
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
-        )));
+        )];
+        if !shown_body.is_empty() {
+            let format = shown_body
+                .sniff_format()
+                .map(|f| format!(", {}", f))
+                .unwrap_or_default();
+            body_title.push(Span::styled(
+                format!(
+                    " ({}{}, {} bytes{}{})",
+                    shown_body.encoding_label(),
+                    format,
+                    shown_body.len(),
+                    if app.wire_view && request.decoded.is_some() {
+                        ", wire"
+                    } else {
+                        ""
+                    },
+                    if app.hex_view { ", hex" } else { "" }
+                ),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        lines.push(Line::from(body_title));
+
+        if let Some(decoded) = &request.decoded {
+            let summary = match &decoded.error {
+                None => Span::styled(
+                    format!(
+                        "{} bytes on the wire -> {} bytes decoded",
+                        request.body.len(),
+                        decoded.body.len()
+                    ),
+                    Style::default().fg(Color::White),
+                ),
+                Some(e) => Span::styled(
+                    format!("decoding failed: {}", e),
+                    Style::default().fg(Color::Red),
+                ),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("  Content-Encoding {}: ", decoded.content_encoding),
+                    Style::default().fg(Color::Yellow),
+                ),
+                summary,
+            ]));
+        }
+
+        let body = if app.hex_view && !shown_body.is_empty() {
+            Some(shown_body.hex_dump())
+        } else {
+            shown_body.formatted()
+        };
 
-        if let Some(body) = request.formatted_body() {
+        if let Some(body) = body {
             let body_lines: Vec<&str> = body.lines().collect();
             let max_lines = if app.body_expanded {
                 body_lines.len()
@@ -192,6 +352,48 @@ fn render_request_details(frame: &mut Frame, app: &App, area: Rect) {
             )));
         }
 
+        // Response
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            "Response:",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )));
+        lines.push(Line::from(vec![
+            Span::styled("  Route: ", Style::default().fg(Color::Gray)),
+            Span::styled(
+                request.route.as_deref().unwrap_or("default"),
+                Style::default().fg(Color::White),
+            ),
+        ]));
+        match (&request.response, &request.dropped) {
+            (Some(response), _) => push_response(&mut lines, response, "  ", app.body_expanded),
+            (None, Some(reason)) => lines.push(Line::from(Span::styled(
+                format!("  No response: {}", reason),
+                Style::default().fg(Color::Red),
+            ))),
+            (None, None) => lines.push(Line::from(Span::styled(
+                "  (pending)",
+                Style::default().fg(Color::DarkGray),
+            ))),
+        }
+
+        push_upstream_results(
+            &mut lines,
+            "Forwarded:",
+            &request.forwards,
+            true,
+            app.body_expanded,
+        );
+        push_upstream_results(
+            &mut lines,
+            "Replays:",
+            &request.replays,
+            false,
+            app.body_expanded,
+        );
+
         // Apply scroll offset
         let visible_lines: Vec<Line> = lines.into_iter().skip(app.detail_scroll).collect();
 
@@ -205,15 +407,213 @@ fn render_request_details(frame: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+/// A titled section listing where a request was sent and what came back.
+/// For forwards, the first result is the one relayed to the caller and the
+/// rest are mirrors.
+fn push_upstream_results(
+    lines: &mut Vec<Line>,
+    title: &'static str,
+    results: &[UpstreamResult],
+    forwarded: bool,
+    expanded: bool,
+) {
+    if results.is_empty() {
+        return;
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        title,
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    )));
+
+    for (i, result) in results.iter().enumerate() {
+        let role = match (forwarded, i) {
+            (false, _) => "",
+            (true, 0) => " (returned to caller)",
+            (true, _) => " (mirror)",
+        };
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!(
+                    "  {} ",
+                    result.timestamp.with_timezone(&Local).format("%H:%M:%S")
+                ),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(result.target.clone(), Style::default().fg(Color::White)),
+            Span::styled(role, Style::default().fg(Color::DarkGray)),
+        ]));
+        match &result.response {
+            Some(response) => push_response(lines, response, "    ", expanded),
+            None => lines.push(Line::from(Span::styled(
+                format!("    {}", result.summary()),
+                Style::default().fg(Color::Red),
+            ))),
+        }
+    }
+}
+
+/// The event attributes, followed by anything that makes an event invalid.
+fn push_cloudevents(lines: &mut Vec<Line>, cloudevents: &CloudEvents) {
+    let title = match cloudevents.mode {
+        Mode::Batch => format!(
+            "CloudEvents ({}, {} events):",
+            cloudevents.mode.label(),
+            cloudevents.events.len()
+        ),
+        mode => format!("CloudEvent ({}):", mode.label()),
+    };
+    lines.push(Line::from(Span::styled(
+        title,
+        Style::default()
+            .fg(Color::Cyan)
+            .add_modifier(Modifier::BOLD),
+    )));
+    if let Some(error) = &cloudevents.error {
+        lines.push(Line::from(Span::styled(
+            format!("  {}", error),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    for (i, event) in cloudevents.events.iter().enumerate() {
+        let indent = if cloudevents.mode == Mode::Batch {
+            lines.push(Line::from(Span::styled(
+                format!("  [{}]", i),
+                Style::default().fg(Color::Gray),
+            )));
+            "    "
+        } else {
+            "  "
+        };
+
+        let core = CORE_ATTRIBUTES
+            .iter()
+            .filter_map(|&name| Some((name, event.get(name)?)));
+        for (name, value) in core.chain(event.extensions()) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{}{}: ", indent, name),
+                    Style::default().fg(Color::Yellow),
+                ),
+                Span::styled(value.to_string(), Style::default().fg(Color::White)),
+            ]));
+        }
+        for problem in &event.problems {
+            lines.push(Line::from(Span::styled(
+                format!("{}invalid: {}", indent, problem),
+                Style::default().fg(Color::Red),
+            )));
+        }
+    }
+}
+
+/// Status, headers and body of a response, indented under its heading.
+/// Bodies are cut to 10 lines unless the body view is expanded.
+fn push_response(lines: &mut Vec<Line>, response: &CapturedResponse, indent: &str, expanded: bool) {
+    let status_color = match response.status {
+        200..=299 => Color::Green,
+        300..=399 => Color::Yellow,
+        _ => Color::Red,
+    };
+    lines.push(Line::from(vec![
+        Span::styled(
+            format!("{}{}", indent, response.status),
+            Style::default()
+                .fg(status_color)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format!(
+                " {} in {:.1} ms",
+                StatusCode::from_u16(response.status)
+                    .ok()
+                    .and_then(|s| s.canonical_reason())
+                    .unwrap_or(""),
+                response.duration_ms
+            ),
+            Style::default().fg(Color::White),
+        ),
+    ]));
+
+    for (key, value) in response.headers.iter() {
+        lines.push(Line::from(vec![
+            Span::styled(
+                format!("{}{}: ", indent, key),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::styled(
+                Headers::text(value).into_owned(),
+                Style::default().fg(Color::White),
+            ),
+        ]));
+    }
+
+    let Some(body) = response.body.formatted() else {
+        return;
+    };
+    let body_lines: Vec<&str> = body.lines().collect();
+    let max_lines = if expanded { body_lines.len() } else { 10 };
+    for line in body_lines.iter().take(max_lines) {
+        lines.push(Line::from(Span::styled(
+            format!("{}{}", indent, line),
+            Style::default().fg(Color::Green),
+        )));
+    }
+    if body_lines.len() > max_lines {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{}... ({} more lines, press Enter to expand)",
+                indent,
+                body_lines.len() - max_lines
+            ),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+}
+
 fn render_status_bar(frame: &mut Frame, app: &App, area: Rect) {
-    let status_text = format!(
-        " Listening on {} | {} request{} | q: quit | c: clear | j/k: navigate | Enter: expand",
+    let session = app
+        .session
+        .as_ref()
+        .map(|name| format!("session {} | ", name))
+        .unwrap_or_default();
+    let mut spans = vec![Span::raw(format!(
+        " Listening on {} ({}) | {}{} request{} | ",
         app.listening_address,
+        app.config_source,
+        session,
         app.requests.len(),
         if app.requests.len() == 1 { "" } else { "s" }
-    );
+    ))];
+
+    let message = match &app.status_message {
+        Some(message) => Some((&message.text, message.is_error)),
+        None => app.config_error.as_ref().map(|error| (error, true)),
+    };
+    if let Some((text, is_error)) = message {
+        let color = if is_error {
+            Color::LightRed
+        } else {
+            Color::LightGreen
+        };
+        // Keep multi-line errors on the single status line
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        spans.push(Span::styled(
+            text,
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        ));
+        spans.push(Span::raw(" | "));
+    }
+
+    spans.push(Span::raw(
+        "q: quit | c: clear | j/k: navigate | Enter: expand | x: hex | w: wire | o: header order | e/E: export | y/Y/H: copy | Space: mark | r: replay",
+    ));
 
-    let status = Paragraph::new(status_text)
+    let status = Paragraph::new(Line::from(spans))
         .style(Style::default().fg(Color::White).bg(Color::DarkGray))
         .block(Block::default().borders(Borders::ALL));
 
@@ -0,0 +1,144 @@
+use chrono::Utc;
+use reqwest::{redirect, Client, Url};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::config::UpstreamConfig;
+use crate::request::{CapturedRequest, CapturedResponse, RequestBody, UpstreamResult};
+
+/// Connection-level headers that describe the hop to this tool, not the
+/// request itself.
+const HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+pub fn is_hop_header(name: &str) -> bool {
+    HOP_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name))
+}
+
+/// Client shared by everything that sends requests on. Redirects are
+/// returned rather than followed, and bodies are passed through without
+/// decompressing them.
+fn client() -> &'static Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .redirect(redirect::Policy::none())
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("HTTP client")
+    })
+}
+
+/// Check that `target` is an http or https base URL.
+pub fn parse_target(target: &str) -> Result<Url, String> {
+    let url = Url::parse(target).map_err(|e| format!("invalid URL {:?}: {}", target, e))?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        other => Err(format!("unsupported scheme {:?} in {:?}", other, target)),
+    }
+}
+
+/// Send `request` to `upstream` with its method, path, query, headers and
+/// wire body. The path is appended to any path already in the target URL.
+/// The upstream's headers are set on top of the request's own; an empty
+/// value removes the header.
+pub async fn send(request: &CapturedRequest, upstream: &UpstreamConfig) -> UpstreamResult {
+    let url = format!(
+        "{}{}",
+        upstream.target.trim_end_matches('/'),
+        request.full_path()
+    );
+    let timestamp = Utc::now();
+
+    let result = exchange(request, &url, upstream.preserve_host, &upstream.headers).await;
+    let (response, error) = match result {
+        Ok(response) => (Some(response), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    UpstreamResult {
+        target: url,
+        timestamp,
+        response,
+        error,
+    }
+}
+
+async fn exchange(
+    request: &CapturedRequest,
+    url: &str,
+    preserve_host: bool,
+    overrides: &HashMap<String, String>,
+) -> Result<CapturedResponse, String> {
+    let url = parse_target(url)?;
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|_| format!("invalid method {:?}", request.method))?;
+
+    let mut builder = client().request(method, url);
+    for (name, value) in request.headers.iter() {
+        let preserved = preserve_host && name.eq_ignore_ascii_case("host");
+        if (is_hop_header(name) && !preserved)
+            || overrides.keys().any(|k| k.eq_ignore_ascii_case(name))
+        {
+            continue;
+        }
+        builder = builder.header(name, value.to_vec());
+    }
+    for (name, value) in overrides {
+        if !value.is_empty() {
+            builder = builder.header(name, value);
+        }
+    }
+    if !request.body.is_empty() {
+        builder = builder.body(request.body.as_bytes().to_vec());
+    }
+
+    let started = Instant::now();
+    let response = builder.send().await.map_err(|e| describe(&e))?;
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_bytes()))
+        .collect();
+    let body = response.bytes().await.map_err(|e| describe(&e))?;
+
+    Ok(CapturedResponse {
+        status,
+        headers,
+        body: RequestBody::from_bytes(&body),
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// reqwest's own message rarely says what went wrong; the innermost cause,
+/// such as "Connection refused", usually does.
+fn describe(error: &reqwest::Error) -> String {
+    let kind = if error.is_timeout() {
+        "timed out"
+    } else if error.is_connect() {
+        "connection failed"
+    } else {
+        "request failed"
+    };
+
+    let mut cause: Option<&dyn Error> = error.source();
+    while let Some(inner) = cause.and_then(|c| c.source()) {
+        cause = Some(inner);
+    }
+    match cause {
+        Some(cause) => format!("{}: {}", kind, cause),
+        None => kind.to_string(),
+    }
+}
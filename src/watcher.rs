@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+use crate::config::{Config, ConfigOverrides};
+use crate::event::AppEvent;
+use crate::logger;
+use crate::server::AppState;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Reload the config whenever `path` changes on disk. A file that fails to
+/// parse is reported and the previous config stays active.
+pub async fn watch_config(
+    path: PathBuf,
+    overrides: ConfigOverrides,
+    state: AppState,
+    tx: mpsc::UnboundedSender<AppEvent>,
+) {
+    let mut last_seen = fingerprint(&path);
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let current = fingerprint(&path);
+        if current == last_seen {
+            continue;
+        }
+        last_seen = current;
+
+        let event = match Config::from_file(&path) {
+            Ok(mut config) => {
+                config.apply_overrides(&overrides);
+                let restart_needed = config.address() != state.config().address();
+                state.replace_config(config);
+                let mut message = format!("Reloaded {}", path.display());
+                if restart_needed {
+                    message.push_str(" (restart to change the listen address)");
+                }
+                logger::write(&message);
+                AppEvent::ConfigReloaded(message)
+            }
+            Err(e) => {
+                logger::write(format!("config error: {}", e));
+                AppEvent::ConfigError(e.to_string())
+            }
+        };
+
+        if tx.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+/// Modification time and size, enough to notice edits and atomic replaces.
+fn fingerprint(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}